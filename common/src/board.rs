@@ -0,0 +1,221 @@
+use crate::Position;
+
+/// Largest radius a [`Board`] can have.
+const MAX_RADIUS: i8 = 7;
+const MAX_SIDE: usize = 2 * MAX_RADIUS as usize + 1;
+/// Marks coordinates in [`Board::hole_indices`] that are not part of the board.
+const NO_HOLE: u8 = u8::MAX;
+
+/// Parameters describing the shape of a board.
+///
+/// Holes are numbered in row-major order over the square from `-radius` to
+/// `radius` in both axes, skipping the coordinates that are not part of the
+/// board. A position stores the hole with index `i` in bit `i`.
+///
+/// The rest of the crate is specialised for [`Board::english`], which is
+/// available in const contexts so that the hot paths don't have to look up
+/// board parameters at runtime. [`Position`] and the coordinate types don't
+/// take a board, so only the English board can actually be played on.
+#[derive(Clone, Copy, Debug)]
+pub struct Board {
+    radius: i8,
+    nr_holes: usize,
+    /// Index of the hole that is empty in the default start position and
+    /// holds the single remaining peg in the default end position.
+    center_idx: u8,
+    /// Bit index of every hole, addressed by `[y + MAX_RADIUS][x + MAX_RADIUS]`,
+    /// or [`NO_HOLE`] for coordinates that are not on the board.
+    hole_indices: [[u8; MAX_SIDE]; MAX_SIDE],
+}
+
+/// Is the given coordinate part of the English cross-shaped board?
+pub(crate) const fn is_english_hole(x: i8, y: i8) -> bool {
+    matches!(
+        (x, y),
+        (-3..=-2, -1..=1) | (-1..=1, -3..=3) | (2..=3, -1..=1)
+    )
+}
+
+impl Board {
+    /// The standard English board with 33 holes arranged in a cross.
+    pub const fn english() -> Board {
+        let mut is_hole = [[false; MAX_SIDE]; MAX_SIDE];
+        let mut y = -3;
+        while y <= 3 {
+            let mut x = -3;
+            while x <= 3 {
+                is_hole[(y + MAX_RADIUS) as usize][(x + MAX_RADIUS) as usize] =
+                    is_english_hole(x, y);
+                x += 1;
+            }
+            y += 1;
+        }
+        Board::from_grid(3, &is_hole)
+    }
+
+    /// Describe a board by the coordinates it contains. The centre 0,0 has to
+    /// be a hole, the radius can be at most 7, and the board can have at most
+    /// 64 holes.
+    pub fn new(radius: i8, is_hole: fn(i8, i8) -> bool) -> Board {
+        assert!(
+            (0..=MAX_RADIUS).contains(&radius),
+            "the radius has to be between 0 and {MAX_RADIUS}"
+        );
+        assert!(is_hole(0, 0), "the centre has to be a hole");
+        let mut grid = [[false; MAX_SIDE]; MAX_SIDE];
+        for y in -radius..=radius {
+            for x in -radius..=radius {
+                grid[(y + MAX_RADIUS) as usize][(x + MAX_RADIUS) as usize] = is_hole(x, y);
+            }
+        }
+        let nr_holes = grid.iter().flatten().filter(|&&h| h).count();
+        assert!(nr_holes <= 64, "a board can have at most 64 holes");
+        Board::from_grid(radius, &grid)
+    }
+
+    /// Number the holes of the grid in row-major order. Only the square of
+    /// the given radius is considered.
+    const fn from_grid(radius: i8, is_hole: &[[bool; MAX_SIDE]; MAX_SIDE]) -> Board {
+        let mut hole_indices = [[NO_HOLE; MAX_SIDE]; MAX_SIDE];
+        let mut nr_holes = 0;
+        let mut y = MAX_RADIUS - radius;
+        while y <= MAX_RADIUS + radius {
+            let mut x = MAX_RADIUS - radius;
+            while x <= MAX_RADIUS + radius {
+                if is_hole[y as usize][x as usize] {
+                    hole_indices[y as usize][x as usize] = nr_holes as u8;
+                    nr_holes += 1;
+                }
+                x += 1;
+            }
+            y += 1;
+        }
+        let center = MAX_RADIUS as usize;
+        Board {
+            radius,
+            nr_holes,
+            center_idx: hole_indices[center][center],
+            hole_indices,
+        }
+    }
+
+    /// The total number of holes on the board.
+    pub const fn nr_holes(&self) -> usize {
+        self.nr_holes
+    }
+
+    /// The number of pegs present in the default start position.
+    pub const fn nr_pegs(&self) -> usize {
+        self.nr_holes - 1
+    }
+
+    /// Bitmask with one bit set for every hole on the board.
+    pub const fn all_holes_mask(&self) -> u64 {
+        u64::MAX >> (64 - self.nr_holes)
+    }
+
+    /// Every hole occupied except for the centre.
+    pub const fn default_start(&self) -> Position {
+        Position(self.all_holes_mask() & !(1u64 << self.center_idx))
+    }
+
+    /// A single peg in the centre.
+    pub const fn default_end(&self) -> Position {
+        Position(1u64 << self.center_idx)
+    }
+
+    /// Is the given coordinate a hole on this board?
+    pub fn contains(&self, x: i8, y: i8) -> bool {
+        self.hole_idx(x, y).is_some()
+    }
+
+    /// All holes on the board, in the order of their bits.
+    pub fn coords(&self) -> impl Iterator<Item = (i8, i8)> {
+        let board = *self;
+        let r = self.radius;
+        (-r..=r).flat_map(move |y| {
+            (-r..=r)
+                .map(move |x| (x, y))
+                .filter(move |&(x, y)| board.contains(x, y))
+        })
+    }
+
+    /// Bit index of the hole at the given coordinate, or `None` if the
+    /// coordinate is not on the board.
+    pub fn hole_idx(&self, x: i8, y: i8) -> Option<u8> {
+        if !(-self.radius..=self.radius).contains(&x) || !(-self.radius..=self.radius).contains(&y)
+        {
+            return None;
+        }
+        let idx = self.hole_indices[(y + MAX_RADIUS) as usize][(x + MAX_RADIUS) as usize];
+        (idx != NO_HOLE).then_some(idx)
+    }
+
+    /// Check that no bits outside of this board are set.
+    pub const fn is_valid_position(&self, pos: Position) -> bool {
+        pos.0 & !self.all_holes_mask() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NR_HOLES, NR_PEGS, coord::Coord};
+
+    #[test]
+    fn test_english_board() {
+        let board = Board::english();
+        assert_eq!(board.nr_holes(), 33);
+        assert_eq!(board.nr_pegs(), 32);
+        assert_eq!(board.nr_holes(), NR_HOLES);
+        assert_eq!(board.nr_pegs(), NR_PEGS);
+        assert_eq!(board.default_start().count(), 32);
+        assert_eq!(board.default_end().count(), 1);
+    }
+
+    #[test]
+    fn test_english_board_matches_generic_construction() {
+        let english = Board::english();
+        let generic = Board::new(3, is_english_hole);
+        assert_eq!(generic.nr_holes(), english.nr_holes());
+        assert_eq!(generic.default_start(), english.default_start());
+        assert_eq!(generic.default_end(), english.default_end());
+    }
+
+    #[test]
+    fn test_english_hole_idx_matches_coord() {
+        let board = Board::english();
+        let coords: Vec<_> = Coord::all().into_iter().collect();
+        assert_eq!(coords.len(), board.nr_holes());
+        for coord in coords {
+            assert_eq!(board.hole_idx(coord.x(), coord.y()), Some(coord.hole_idx()));
+        }
+        assert_eq!(board.hole_idx(3, 3), None);
+    }
+
+    #[test]
+    fn test_custom_board() {
+        // a plain 3x3 square
+        let board = Board::new(1, |_, _| true);
+        assert_eq!(board.nr_holes(), 9);
+        assert_eq!(board.default_end(), Position(1 << 4));
+        assert_eq!(board.default_start().count(), 8);
+        assert!(board.is_valid_position(Position(0x1ff)));
+        assert!(!board.is_valid_position(Position(0x200)));
+    }
+
+    #[test]
+    fn test_custom_board_hole_idx() {
+        // a plus sign with arms of length two
+        let board = Board::new(2, |x, y| x == 0 || y == 0);
+        assert_eq!(board.nr_holes(), 9);
+        let coords: Vec<_> = board.coords().collect();
+        for (idx, &(x, y)) in coords.iter().enumerate() {
+            assert_eq!(board.hole_idx(x, y), Some(idx as u8));
+        }
+        assert_eq!(board.hole_idx(1, 1), None);
+        assert_eq!(board.hole_idx(3, 0), None);
+        assert_eq!(board.hole_idx(-8, 0), None);
+        assert_eq!(board.default_end(), Position(1 << 4));
+    }
+}
@@ -1,15 +1,55 @@
 use std::{fmt::Display, ops::Sub};
 
+use crate::{NR_HOLES, board::is_english_hole};
+
 /// A hole coordinate on the board.
 /// Centre hole is 0,0, x increases to the right, y increases downwards.
 ///
-/// Invariant: can only represent valid coordinates
+/// Invariant: can only represent valid coordinates on the English board
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub struct Coord {
     x: i8,
     y: i8,
 }
 
+/// One of the four arms protruding from the 3x3 centre block of the board.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Arm {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+/// One of the four directions in which a peg can jump.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum CardinalDir {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl CardinalDir {
+    pub const ALL: [CardinalDir; 4] = [
+        CardinalDir::Up,
+        CardinalDir::Down,
+        CardinalDir::Left,
+        CardinalDir::Right,
+    ];
+
+    /// Offset of a single step in this direction, in the coordinate system
+    /// of [`Coord`].
+    pub const fn offset(self) -> (i8, i8) {
+        match self {
+            CardinalDir::Up => (0, -1),
+            CardinalDir::Down => (0, 1),
+            CardinalDir::Left => (-1, 0),
+            CardinalDir::Right => (1, 0),
+        }
+    }
+}
+
 impl Sub for Coord {
     type Output = (i8, i8);
 
@@ -19,6 +59,27 @@ impl Sub for Coord {
 }
 
 impl Coord {
+    /// Every coordinate on the board, ordered row by row from the top left.
+    /// This is the same order as the hole indices, so `Coord::ALL[i]` is the
+    /// hole with index `i`.
+    pub const ALL: [Coord; NR_HOLES] = {
+        let mut coords = [Coord::center(); NR_HOLES];
+        let mut idx = 0;
+        let mut y = -3;
+        while y <= 3 {
+            let mut x = -3;
+            while x <= 3 {
+                if is_english_hole(x, y) {
+                    coords[idx] = Coord { x, y };
+                    idx += 1;
+                }
+                x += 1;
+            }
+            y += 1;
+        }
+        coords
+    };
+
     pub const fn new(x: i8, y: i8) -> Option<Self> {
         let coord = Coord { x, y };
         if coord.is_valid() { Some(coord) } else { None }
@@ -45,6 +106,11 @@ impl Coord {
         idx as u8
     }
 
+    /// Inverse of [`Coord::hole_idx`].
+    pub fn from_hole_idx(idx: u8) -> Option<Coord> {
+        Coord::ALL.get(idx as usize).copied()
+    }
+
     pub const fn bitmask(self) -> u64 {
         1u64 << self.hole_idx()
     }
@@ -61,14 +127,71 @@ impl Coord {
     }
 
     const fn is_valid(self) -> bool {
-        matches!(
-            (self.x, self.y),
-            (-3..=-2, -1..=1) | (-1..=1, -3..=3) | (2..=3, -1..=1)
-        )
+        is_english_hole(self.x, self.y)
     }
 
+    /// Iterate over [`Coord::ALL`], i.e. every coordinate in the order of the
+    /// hole indices.
     pub fn all() -> impl IntoIterator<Item = Self> {
-        (-3..=3).flat_map(|y| (-3..=3).filter_map(move |x| Coord::new(x, y)))
+        Coord::ALL
+    }
+
+    /// Is this coordinate within the 3x3 block in the centre of the board?
+    pub const fn is_center_block(self) -> bool {
+        self.x.abs() <= 1 && self.y.abs() <= 1
+    }
+
+    /// Get the arm that this coordinate belongs to, or `None` if it lies
+    /// within the centre block.
+    pub const fn arm(self) -> Option<Arm> {
+        if self.y <= -2 {
+            Some(Arm::Top)
+        } else if self.y >= 2 {
+            Some(Arm::Bottom)
+        } else if self.x <= -2 {
+            Some(Arm::Left)
+        } else if self.x >= 2 {
+            Some(Arm::Right)
+        } else {
+            None
+        }
+    }
+
+    /// Is this the centre hole of the board?
+    pub const fn is_center(self) -> bool {
+        self.x == 0 && self.y == 0
+    }
+
+    /// Is this one of the eight corners at the outer end of the arms?
+    pub const fn is_corner(self) -> bool {
+        let (x, y) = (self.x.abs(), self.y.abs());
+        matches!((x, y), (1, 3) | (3, 1))
+    }
+
+    /// Number of steps between the two coordinates when only moving along
+    /// the axes.
+    pub const fn manhattan_distance(self, other: Self) -> u8 {
+        let (dx, dy) = self.subtract(other);
+        dx.unsigned_abs() + dy.unsigned_abs()
+    }
+
+    /// Parse a coordinate in the usual notation, with the columns `a` to `g`
+    /// from left to right and the rows `1` to `7` from top to bottom. The
+    /// centre is `d4`.
+    pub fn from_notation(s: &str) -> Option<Coord> {
+        let mut chars = s.chars();
+        let (col, row) = (chars.next()?, chars.next()?);
+        if chars.next().is_some() || !('a'..='g').contains(&col) || !('1'..='7').contains(&row) {
+            return None;
+        }
+        Coord::new(col as i8 - 'd' as i8, row as i8 - '4' as i8)
+    }
+
+    /// Inverse of [`Coord::from_notation`].
+    pub fn to_notation(self) -> String {
+        let col = (b'd' as i8 + self.x) as u8 as char;
+        let row = (b'4' as i8 + self.y) as u8 as char;
+        format!("{col}{row}")
     }
 
     pub fn x(self) -> i8 {
@@ -99,4 +222,81 @@ mod tests {
     fn test_topleft_should_be_lowest_bit() {
         assert_eq!(Coord::new(-1, -3).unwrap().bitmask(), 1);
     }
+
+    #[test]
+    fn test_from_hole_idx_inverts_hole_idx() {
+        for coord in Coord::all() {
+            assert_eq!(Coord::from_hole_idx(coord.hole_idx()), Some(coord));
+        }
+        assert_eq!(Coord::from_hole_idx(33), None);
+    }
+
+    #[test]
+    fn test_all_is_ordered_by_hole_idx() {
+        for (i, coord) in Coord::ALL.into_iter().enumerate() {
+            assert_eq!(coord.hole_idx() as usize, i);
+        }
+        assert!(Coord::all().into_iter().eq(Coord::ALL));
+    }
+
+    #[test]
+    fn test_region_classification() {
+        let c = |x, y| Coord::new(x, y).unwrap();
+
+        assert!(c(0, 0).is_center_block());
+        assert!(c(-1, 1).is_center_block());
+        assert!(!c(0, -2).is_center_block());
+
+        assert_eq!(c(0, 0).arm(), None);
+        assert_eq!(c(1, -1).arm(), None);
+        assert_eq!(c(-1, -3).arm(), Some(Arm::Top));
+        assert_eq!(c(1, 2).arm(), Some(Arm::Bottom));
+        assert_eq!(c(-3, 1).arm(), Some(Arm::Left));
+        assert_eq!(c(2, 0).arm(), Some(Arm::Right));
+
+        for coord in Coord::all() {
+            assert_ne!(coord.is_center_block(), coord.arm().is_some());
+        }
+    }
+
+    #[test]
+    fn test_notation() {
+        assert_eq!(Coord::from_notation("d4"), Some(Coord::center()));
+        assert_eq!(Coord::from_notation("c1"), Coord::new(-1, -3));
+        assert_eq!(Coord::from_notation("g5"), Coord::new(3, 1));
+        assert_eq!(Coord::from_notation("a1"), None);
+        assert_eq!(Coord::from_notation("h4"), None);
+        assert_eq!(Coord::from_notation("d44"), None);
+        assert_eq!(Coord::from_notation("d"), None);
+
+        for coord in Coord::all() {
+            assert_eq!(Coord::from_notation(&coord.to_notation()), Some(coord));
+        }
+    }
+
+    #[test]
+    fn test_geometric_helpers() {
+        let c = |x, y| Coord::new(x, y).unwrap();
+        let center = Coord::center();
+        let corner = c(-1, -3);
+        let edge = c(3, 0);
+
+        assert!(center.is_center());
+        assert!(!corner.is_center());
+        assert!(!edge.is_center());
+
+        assert!(!center.is_corner());
+        assert!(corner.is_corner());
+        assert!(!edge.is_corner());
+        assert_eq!(
+            Coord::all().into_iter().filter(|c| c.is_corner()).count(),
+            8
+        );
+
+        assert_eq!(center.manhattan_distance(center), 0);
+        assert_eq!(corner.manhattan_distance(center), 4);
+        assert_eq!(edge.manhattan_distance(center), 3);
+        assert_eq!(corner.manhattan_distance(edge), 7);
+        assert_eq!(edge.manhattan_distance(corner), 7);
+    }
 }
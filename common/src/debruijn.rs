@@ -1,6 +1,9 @@
-use std::ops::{Add, AddAssign, Mul, MulAssign};
+use std::{
+    fmt::Display,
+    ops::{Add, AddAssign, Mul, MulAssign},
+};
 
-use crate::{coord::Coord, Position};
+use crate::{Direction, Move, Position, all_moves, coord::Coord};
 
 /// Galois Field with four elements.
 ///
@@ -14,6 +17,18 @@ pub enum GF4 {
     Q,
 }
 
+impl Display for GF4 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            GF4::Zero => "0",
+            GF4::One => "1",
+            GF4::P => "p",
+            GF4::Q => "q",
+        };
+        write!(f, "{s}")
+    }
+}
+
 impl AddAssign for GF4 {
     fn add_assign(&mut self, rhs: Self) {
         *self = *self + rhs;
@@ -108,6 +123,25 @@ pub fn de_bruijn_solvable(pos: Position) -> bool {
     de_bruijn_class(pos) == (GF4::One, GF4::One)
 }
 
+/// The legal moves in the given direction that lead to a position which is
+/// not [`de_bruijn_solvable`].
+///
+/// A single move never changes the class of a position, so this is empty for
+/// every de Bruijn solvable position, and contains all legal moves otherwise.
+pub fn de_bruijn_breaking_moves(pos: Position, dir: Direction) -> Vec<Move> {
+    all_moves()
+        .into_iter()
+        .filter_map(|mv| match dir {
+            Direction::Forward => pos.can_move(mv).then(|| (mv, pos.apply_move(mv))),
+            Direction::Backward => pos
+                .can_move_inverse(mv)
+                .then(|| (mv, pos.apply_move_inverse(mv))),
+        })
+        .filter(|&(_, child)| !de_bruijn_solvable(child))
+        .map(|(mv, _)| mv)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -131,6 +165,15 @@ mod tests {
         );
     }
 
+    #[test]
+    fn display() {
+        let names: Vec<_> = [GF4::Zero, GF4::One, GF4::P, GF4::Q]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(names, ["0", "1", "p", "q"]);
+    }
+
     #[test]
     fn empty_board() {
         assert_eq!(de_bruijn_class(Position(0)), (GF4::Zero, GF4::Zero));
@@ -142,6 +185,29 @@ mod tests {
     }
 
     #[test]
+    fn moves_never_break_the_class() {
+        for seed in 0..20 {
+            let pos = Position::random_solvable(seed as i32 + 2, seed);
+            for dir in [Direction::Forward, Direction::Backward] {
+                assert_eq!(de_bruijn_breaking_moves(pos, dir), vec![]);
+            }
+        }
+
+        // Starting from a different class, every legal move stays outside of
+        // the solvable class.
+        let pos = Position::default_start().apply_move(Move::from_raw_coords((0, 2), (0, 0)));
+        let pos = Position(pos.0 & !Coord::new(-1, 0).unwrap().bitmask());
+        assert!(!de_bruijn_solvable(pos));
+        let legal_moves: Vec<_> = pos.legal_moves().collect();
+        assert!(!legal_moves.is_empty());
+        assert_eq!(
+            de_bruijn_breaking_moves(pos, Direction::Forward),
+            legal_moves
+        );
+    }
+
+    #[test]
+    #[allow(clippy::identity_op)]
     fn exapmle_situation_from_paper() {
         assert_eq!(GF4::P.pow(-1 + 1), GF4::One);
         assert_eq!(GF4::P.pow(0 + 2), GF4::Q);
@@ -1,25 +1,62 @@
+pub mod board;
 pub mod coord;
 pub mod debruijn;
+pub mod position_set;
+pub mod replay;
+pub mod svg;
 
 #[cfg(not(target_family = "wasm"))]
 use std::path::Path;
 use std::{
+    cmp::Reverse,
     fmt::{Debug, Display},
     ops::Not,
+    sync::LazyLock,
 };
 
 use bincode::config;
 use bitvec::{bitbox, boxed::BitBox, prelude::Lsb0};
-use rand::{SeedableRng, seq::SliceRandom};
+use rand::{
+    Rng, SeedableRng,
+    seq::{IteratorRandom, SliceRandom},
+};
 use rand_pcg::Pcg64Mcg;
+use rustc_hash::{FxHashMap, FxHashSet};
 
-use crate::{coord::Coord, debruijn::de_bruijn_solvable};
+use crate::{
+    board::Board,
+    coord::{CardinalDir, Coord},
+    debruijn::{de_bruijn_class, de_bruijn_solvable},
+};
 
 /// The number of pegs present in the default start position.
-pub const NR_PEGS: usize = 32;
+pub const NR_PEGS: usize = Board::english().nr_pegs();
 
 /// The total number of holes on the board.
-pub const NR_HOLES: usize = 33;
+pub const NR_HOLES: usize = Board::english().nr_holes();
+
+/// Bitmask with one bit set for every hole on the board.
+const ALL_HOLES_MASK: u64 = Board::english().all_holes_mask();
+
+/// Spread the 33 hole bits of a position onto a grid with 8 bits per row,
+/// where the hole at `(x, y)` ends up at bit `(y + 3) * 8 + (x + 3)`. On this
+/// grid a step in any direction is a constant shift, and the unused eighth
+/// column stops horizontal shifts from wrapping into the next row.
+const fn spread_to_grid(bits: u64) -> u64 {
+    (bits & 0x7) << 2
+        | (bits >> 3 & 0x7) << 10
+        | (bits >> 6 & 0x7f) << 16
+        | (bits >> 13 & 0x7f) << 24
+        | (bits >> 20 & 0x7f) << 32
+        | (bits >> 27 & 0x7) << 42
+        | (bits >> 30 & 0x7) << 50
+}
+
+/// Number of holes in each row of the board, from top to bottom.
+const ROW_LENGTHS: [u32; 7] = [3, 3, 7, 7, 7, 3, 3];
+
+/// All holes of the board in the layout of [`spread_to_grid`].
+const ALL_HOLES_GRID: u64 = spread_to_grid(ALL_HOLES_MASK);
 
 /// A game position stored as a bitfield. For every hole we store if it is
 /// empty (stored as zero) or occupied by a peg (stored as one).
@@ -66,6 +103,28 @@ impl Move {
         })
     }
 
+    /// The move that jumps the peg at `src` two holes in the given direction.
+    /// Returns `None` if the destination is off the board.
+    pub const fn from_src_dir(src: Coord, dir: CardinalDir) -> Option<Move> {
+        let (dx, dy) = dir.offset();
+        match src.shift(2 * dx, 2 * dy) {
+            Some(dst) => Move::from_coords(src, dst),
+            None => None,
+        }
+    }
+
+    /// The direction in which the peg jumps, inverse of
+    /// [`Move::from_src_dir`].
+    pub const fn direction(self) -> CardinalDir {
+        match self.dst.subtract(self.src) {
+            (2, 0) => CardinalDir::Right,
+            (-2, 0) => CardinalDir::Left,
+            (0, 2) => CardinalDir::Down,
+            (0, -2) => CardinalDir::Up,
+            _ => unreachable!(),
+        }
+    }
+
     /// Utility function for manually creating moves, useful in tests.
     ///
     /// Panics when coordinates are out of bounds or coordinates are not exactly
@@ -74,6 +133,29 @@ impl Move {
         Move::from_coords(Coord::new(x1, y1).unwrap(), Coord::new(x2, y2).unwrap()).unwrap()
     }
 
+    /// Parse a move in the notation `d6-d4`, i.e. the source and destination
+    /// coordinates separated by a dash. See [`Coord::from_notation`].
+    pub fn from_notation(s: &str) -> Option<Move> {
+        let (src, dst) = s.trim().split_once('-')?;
+        Move::from_coords(Coord::from_notation(src)?, Coord::from_notation(dst)?)
+    }
+
+    /// Inverse of [`Move::from_notation`].
+    pub fn to_notation(self) -> String {
+        format!("{}-{}", self.src.to_notation(), self.dst.to_notation())
+    }
+
+    /// Position of this move in the fixed ordering of [`all_moves`], which
+    /// allows storing a move in a single byte.
+    pub fn index(self) -> u8 {
+        MOVE_INDICES[self.src.hole_idx() as usize][self.direction() as usize]
+    }
+
+    /// Inverse of [`Move::index`]. Returns `None` if the index is out of range.
+    pub fn from_index(idx: u8) -> Option<Move> {
+        ALL_MOVES.get(idx as usize).copied()
+    }
+
     /// Get the coordinate where the jumping peg starts.
     pub const fn source(self) -> Coord {
         self.src
@@ -89,8 +171,133 @@ impl Move {
     pub const fn middle(self) -> Coord {
         self.middle
     }
+
+    /// Get the three coordinates whose occupation changes with this move, in
+    /// the order source, middle, destination.
+    pub const fn changed_coords(self) -> [Coord; 3] {
+        [self.src, self.middle, self.dst]
+    }
+
+    /// Get the coordinate that receives a peg when the move is performed in
+    /// the forward direction.
+    pub const fn added(self) -> Coord {
+        self.dst
+    }
+
+    /// Get the coordinates that lose their pegs when the move is performed in
+    /// the forward direction.
+    pub const fn removed(self) -> [Coord; 2] {
+        [self.src, self.middle]
+    }
+
+    /// Get the step that undoes this move, such that applying the inverse is
+    /// the same as applying this move backward.
+    pub const fn inverse(self) -> InverseMove {
+        InverseMove(self)
+    }
+}
+
+/// A jump played in reverse, as returned by [`Move::inverse`]. The peg jumps
+/// back from the destination to the source and the jumped peg is put back.
+///
+/// This is not a jump in its own right, since it removes one peg and adds
+/// two, so it is kept apart from [`Move`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct InverseMove(Move);
+
+impl InverseMove {
+    /// The jump that this step undoes.
+    pub const fn inverse(self) -> Move {
+        self.0
+    }
+
+    /// Get the coordinate where the peg starts, i.e. the destination of the
+    /// original jump.
+    pub const fn source(self) -> Coord {
+        self.0.dst
+    }
+
+    /// Get the coordinate where the peg ends up, i.e. the source of the
+    /// original jump.
+    pub const fn destination(self) -> Coord {
+        self.0.src
+    }
+
+    /// Can the step be performed in the given position? Same as
+    /// [`Position::can_move_inverse`] with the original jump.
+    pub fn is_legal_in(self, pos: Position) -> bool {
+        pos.can_move_inverse(self.0)
+    }
+
+    /// Perform the step, which must be legal in the given position. Same as
+    /// [`Position::apply_move_inverse`] with the original jump.
+    pub fn apply_to(self, pos: Position) -> Position {
+        pos.apply_move_inverse(self.0)
+    }
+}
+
+/// Error when applying a move given in notation to a position.
+#[derive(Debug, PartialEq, Eq)]
+pub enum NotationError {
+    /// The string is not a valid move.
+    InvalidNotation(String),
+    /// The move can't be performed in the position.
+    IllegalMove(Move),
+}
+
+impl Display for NotationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotationError::InvalidNotation(s) => write!(f, "invalid move notation {s:?}"),
+            NotationError::IllegalMove(mv) => write!(f, "illegal move {}", mv.to_notation()),
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+/// Content of a hole as returned by [`Position::hole_state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoleState {
+    Peg,
+    Empty,
+    /// The coordinates are not on the board.
+    Invalid,
+}
+
+/// Error when parsing a position with [`Position::from_multiline`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParsePositionError {
+    /// The text doesn't consist of exactly seven non-blank lines.
+    WrongNumberOfLines(usize),
+    /// The non-blank line with the given index has the wrong number of holes.
+    WrongLineLength {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// The text contains a character other than `#`, `.` and whitespace.
+    InvalidChar(char),
+}
+
+impl Display for ParsePositionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParsePositionError::WrongNumberOfLines(n) => {
+                write!(f, "expected 7 lines, found {n}")
+            }
+            ParsePositionError::WrongLineLength {
+                line,
+                expected,
+                found,
+            } => write!(f, "expected {expected} holes in line {line}, found {found}"),
+            ParsePositionError::InvalidChar(c) => write!(f, "invalid char {c:?}"),
+        }
+    }
 }
 
+impl std::error::Error for ParsePositionError {}
+
 impl Position {
     pub fn from_ascii(lines: [&str; 7]) -> Self {
         let mut position = 0;
@@ -118,31 +325,105 @@ impl Position {
         if current_peg_bitmask < max_bitmask {
             panic!("not enough chars in ascii");
         }
-        Self(position)
+        let position = Self(position);
+        position.debug_assert_no_stray_bits();
+        position
     }
 
-    pub fn default_start() -> Position {
-        Self::from_ascii([
-            "    ###    ",
-            "    ###    ",
-            "  #######  ",
-            "  ###.###  ",
-            "  #######  ",
-            "    ###    ",
-            "    ###    ",
-        ])
+    /// Parse a position in the format of [`Position::from_ascii`], but from a
+    /// single string and without panicking.
+    ///
+    /// Blank lines and any whitespace within the lines are ignored, so boards
+    /// that were copied from somewhere else with different indentation can
+    /// be pasted directly.
+    pub fn from_multiline(text: &str) -> Result<Self, ParsePositionError> {
+        let lines: Vec<_> = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        if lines.len() != 7 {
+            return Err(ParsePositionError::WrongNumberOfLines(lines.len()));
+        }
+
+        let mut position = 0;
+        let mut current_peg_bitmask = 1;
+        for (line_idx, line) in lines.into_iter().enumerate() {
+            let expected = Coord::all()
+                .into_iter()
+                .filter(|coord| coord.y() + 3 == line_idx as i8)
+                .count();
+            let mut found = 0;
+            for c in line.chars().filter(|c| !c.is_whitespace()) {
+                match c {
+                    '.' => {}
+                    '#' => position |= current_peg_bitmask,
+                    _ => return Err(ParsePositionError::InvalidChar(c)),
+                }
+                found += 1;
+                current_peg_bitmask <<= 1;
+            }
+            if found != expected {
+                return Err(ParsePositionError::WrongLineLength {
+                    line: line_idx,
+                    expected,
+                    found,
+                });
+            }
+        }
+
+        Ok(Self(position))
     }
 
-    pub fn default_end() -> Position {
-        Self::from_ascii([
-            "    ...    ",
-            "    ...    ",
-            "  .......  ",
-            "  ...#...  ",
-            "  .......  ",
-            "    ...    ",
-            "    ...    ",
-        ])
+    /// Check that no bits outside of the board are set. Such bits can only
+    /// appear when a `Position` is constructed from a raw integer.
+    pub fn is_valid(&self) -> bool {
+        Board::english().is_valid_position(*self)
+    }
+
+    fn debug_assert_no_stray_bits(&self) {
+        debug_assert!(
+            self.is_valid(),
+            "position {:#x} has bits set outside of the board",
+            self.0
+        );
+    }
+
+    pub const fn default_start() -> Position {
+        Board::english().default_start()
+    }
+
+    pub const fn default_end() -> Position {
+        Board::english().default_end()
+    }
+
+    /// A full board with only the given hole empty, e.g. for the variants that
+    /// start with an empty corner. [`Position::default_start`] is the special
+    /// case with the empty centre.
+    pub const fn full_board_except(coord: Coord) -> Position {
+        Position(ALL_HOLES_MASK & !coord.bitmask())
+    }
+
+    /// All positions from which a single move leads to
+    /// [`Position::default_end`]. Since the last jump has to land in the
+    /// centre, there are only four of them.
+    pub fn predecessors_of_end() -> Vec<Position> {
+        Position::default_end().predecessors()
+    }
+
+    /// All positions from which a single move leads to this position.
+    pub fn predecessors(&self) -> Vec<Position> {
+        self.iter_predecessors().collect()
+    }
+
+    /// Like [`Position::predecessors`], but without allocating, for hot
+    /// loops.
+    pub fn iter_predecessors(&self) -> impl Iterator<Item = Position> + use<> {
+        let pos = *self;
+        ALL_MOVES
+            .iter()
+            .copied()
+            .filter(move |&mv| pos.can_move_inverse(mv))
+            .map(move |mv| pos.apply_move_inverse(mv))
     }
 
     /// Number of occupied holes in this position
@@ -150,30 +431,100 @@ impl Position {
         self.0.count_ones() as i32
     }
 
+    /// Number of occupied holes among the holes selected by `mask`. Bits
+    /// outside of the board are ignored.
+    pub fn count_in(&self, mask: u64) -> u32 {
+        (self.0 & mask & ALL_HOLES_MASK).count_ones()
+    }
+
+    /// A lower bound on the number of moves needed to get from this position
+    /// to `end`.
+    ///
+    /// Every move removes exactly one peg, so if `end` is reachable at all
+    /// then the peg count difference is the exact distance, which makes
+    /// this bound admissible. A pagoda function can't improve on it, since
+    /// pagoda functions can only rule out reachability and don't say anything
+    /// about the number of moves.
+    pub fn distance_to_end_lower_bound(&self, end: Position) -> i32 {
+        self.count() - end.count()
+    }
+
+    /// A lower bound on the number of moves between this position and
+    /// `other`, in either direction, based only on the holes that differ.
+    ///
+    /// Every move changes exactly three holes and removes one peg, so at
+    /// least a third of the differing holes and the peg count difference
+    /// both need a move each.
+    pub fn peg_distance(&self, other: &Position) -> u32 {
+        let nr_changed = (self.as_bits() ^ other.as_bits()).count_ones();
+        let nr_pegs_difference = self.count().abs_diff(other.count());
+        nr_changed.div_ceil(3).max(nr_pegs_difference)
+    }
+
+    /// Swap pegs and holes. Any stray bits outside of the board are dropped.
     pub fn inverse(&self) -> Self {
-        Self(self.0 ^ ((1u64 << 33) - 1))
+        Self((self.0 & ALL_HOLES_MASK) ^ ALL_HOLES_MASK)
+    }
+
+    /// The raw bitfield with any stray bits outside of the board cleared.
+    pub fn as_bits(&self) -> u64 {
+        self.0 & ALL_HOLES_MASK
+    }
+
+    /// Build a position from a raw bitfield, dropping any bits outside of the
+    /// board. Use this instead of the tuple constructor when the bits come
+    /// from code that doesn't uphold [`Position::is_valid`].
+    pub fn from_bits(bits: u64) -> Position {
+        Position(bits & ALL_HOLES_MASK)
+    }
+
+    /// Build a position from a raw bitfield, or `None` if any bits outside of
+    /// the board are set. See [`Position::from_bits`] for a version that
+    /// drops those bits instead.
+    pub fn new(bits: u64) -> Option<Position> {
+        let pos = Position(bits);
+        pos.is_valid().then_some(pos)
     }
 
     pub fn can_move(&self, mv: Move) -> bool {
-        (self.0 & mv.add_bits) == 0 && (self.0 & mv.remove_bits).count_ones() == 2
+        (self.0 & mv.add_bits) == 0 && (self.0 & mv.remove_bits) == mv.remove_bits
     }
     pub fn can_move_inverse(&self, mv: Move) -> bool {
-        (self.0 & mv.remove_bits) == 0 && (self.0 & mv.add_bits) > 0
+        (self.0 & mv.remove_bits) == 0 && (self.0 & mv.add_bits) == mv.add_bits
     }
+    /// Perform the move. The move must be legal in this position, which is
+    /// only checked in debug builds.
     pub fn apply_move(&self, mv: Move) -> Position {
+        debug_assert!(self.can_move(mv), "illegal move {mv:?} in {self:?}");
         let mut next = self.0;
         next &= !mv.remove_bits;
         next |= mv.add_bits;
         Position(next)
     }
+    /// Parse a move with [`Move::from_notation`] and perform it.
+    pub fn after_notation(&self, notation: &str) -> Result<Position, NotationError> {
+        let mv = Move::from_notation(notation)
+            .ok_or_else(|| NotationError::InvalidNotation(notation.to_string()))?;
+        if !self.can_move(mv) {
+            return Err(NotationError::IllegalMove(mv));
+        }
+        Ok(self.apply_move(mv))
+    }
+    /// Undo the move. The move must be legal to undo in this position, which
+    /// is only checked in debug builds.
     pub fn apply_move_inverse(&self, mv: Move) -> Position {
+        debug_assert!(
+            self.can_move_inverse(mv),
+            "illegal inverse move {mv:?} in {self:?}"
+        );
         let mut next = self.0;
         next |= mv.remove_bits;
         next &= !mv.add_bits;
         Position(next)
     }
+    #[allow(clippy::identity_op)]
     pub fn rotate(&self) -> Position {
-        let pos = self.0 & ((1u64 << 33) - 1);
+        let pos = self.0 & ALL_HOLES_MASK;
 
         let mut out = 0;
         let mut out_mask = 1;
@@ -211,8 +562,9 @@ impl Position {
         Position(out)
     }
 
+    #[allow(clippy::identity_op)]
     pub fn mirror(&self) -> Position {
-        let pos = self.0 & ((1u64 << 33) - 1);
+        let pos = self.0 & ALL_HOLES_MASK;
 
         let mut out = 0;
         let short_row_mask: u64 = 0b111;
@@ -229,7 +581,40 @@ impl Position {
         Position(out)
     }
 
-    pub fn normalize(&self) -> Position {
+    /// Split the position into the seven rows of the board, from top to
+    /// bottom. Bit `i` of a row is its `i`-th hole from the left, so the short
+    /// rows of the top and bottom arms use three bits and the long rows use
+    /// seven.
+    ///
+    /// This matches the bit layout of the position, where the rows are stored
+    /// one after another, and is meant for checking the geometry code.
+    pub fn rows(&self) -> [u8; 7] {
+        let mut shift = 0;
+        ROW_LENGTHS.map(|len| {
+            let row = (self.0 >> shift) & ((1 << len) - 1);
+            shift += len;
+            row as u8
+        })
+    }
+
+    /// Inverse of [`Position::rows`]. Returns `None` if a row has bits set
+    /// beyond its length.
+    pub fn from_rows(rows: [u8; 7]) -> Option<Position> {
+        let mut bits = 0;
+        let mut shift = 0;
+        for (row, len) in rows.into_iter().zip(ROW_LENGTHS) {
+            if row >> len != 0 {
+                return None;
+            }
+            bits |= (row as u64) << shift;
+            shift += len;
+        }
+        Some(Position(bits))
+    }
+
+    /// The images of this position under all eight symmetries of the board,
+    /// starting with the identity.
+    fn symmetric_images(&self) -> [Position; 8] {
         let mut candidates = [*self; 8];
 
         for i in 1..4 {
@@ -239,12 +624,394 @@ impl Position {
             candidates[i] = candidates[i - 4].mirror();
         }
 
+        candidates
+    }
+
+    pub fn normalize(&self) -> Position {
+        let candidates = self.symmetric_images();
         Position(candidates.iter().map(|p| p.0).min().unwrap())
     }
 
+    /// Number of symmetries of the board that map this position onto itself.
+    ///
+    /// This is 1 for a position without any symmetry, and 8 for positions
+    /// like the default start that are symmetric in every way.
+    pub fn symmetry_count(&self) -> u32 {
+        let candidates = self.symmetric_images();
+        candidates.iter().filter(|&p| p == self).count() as u32
+    }
+
     pub fn is_occupied(&self, coord: Coord) -> bool {
         self.0 & coord.bitmask() > 0
     }
+
+    /// Look up the hole at the given coordinates relative to the centre,
+    /// which don't have to be on the board.
+    pub fn hole_state(&self, x: i8, y: i8) -> HoleState {
+        match Coord::new(x, y) {
+            None => HoleState::Invalid,
+            Some(coord) if self.is_occupied(coord) => HoleState::Peg,
+            Some(_) => HoleState::Empty,
+        }
+    }
+
+    /// Is every peg of this position also present in `other`?
+    pub fn is_subset_of(&self, other: &Position) -> bool {
+        self.0 & other.0 == self.0
+    }
+
+    /// Iterate over all moves that can be performed in this position.
+    pub fn legal_moves(&self) -> impl Iterator<Item = Move> + use<> {
+        let pos = *self;
+        ALL_MOVES
+            .iter()
+            .copied()
+            .filter(move |&mv| pos.can_move(mv))
+    }
+
+    /// Count the legal moves in this position.
+    ///
+    /// Gives the same result as `self.legal_moves().count()`, but checks all
+    /// moves in one direction at once with a few shifts instead of testing
+    /// each of the 76 moves individually.
+    pub fn num_legal_moves(&self) -> u32 {
+        self.legal_move_mask()
+            .iter()
+            .map(|mask| mask.count_ones())
+            .sum()
+    }
+
+    /// The pegs that can jump, for each direction in the order of
+    /// [`MOVE_DIRECTIONS`]. The masks are laid out as a grid with 8 bits per
+    /// row, i.e. the peg at `(x, y)` is bit `(y + 3) * 8 + (x + 3)`.
+    ///
+    /// All sources in one direction are found at once with a few shifts,
+    /// instead of testing each of the 76 moves individually.
+    pub fn legal_move_mask(&self) -> [u64; 4] {
+        let pegs = spread_to_grid(self.0 & ALL_HOLES_MASK);
+        let holes = !pegs & ALL_HOLES_GRID;
+
+        MOVE_DIRECTIONS.map(|dir| {
+            let (dx, dy) = dir.offset();
+            let step = dx as i32 + 8 * dy as i32;
+            let step_abs = step.unsigned_abs();
+            // Horizontal jumps that would wrap around into another row pass
+            // through the unused eighth column, which never holds a peg.
+            if step > 0 {
+                pegs & pegs >> step_abs & holes >> (2 * step_abs)
+            } else {
+                pegs & pegs << step_abs & holes << (2 * step_abs)
+            }
+        })
+    }
+
+    /// Iterate over the legal moves based on [`Position::legal_move_mask`].
+    ///
+    /// This gives the same moves as [`Position::legal_moves`], but grouped by
+    /// direction in the order of [`MOVE_DIRECTIONS`] and ordered by the hole
+    /// index of the source within each direction.
+    pub fn legal_moves_by_direction(&self) -> impl Iterator<Item = Move> + use<> {
+        MOVE_DIRECTIONS
+            .into_iter()
+            .zip(self.legal_move_mask())
+            .flat_map(|(dir, mut mask)| {
+                std::iter::from_fn(move || {
+                    if mask == 0 {
+                        return None;
+                    }
+                    let bit = mask.trailing_zeros();
+                    mask &= mask - 1;
+                    let src = Coord::new((bit % 8) as i8 - 3, (bit / 8) as i8 - 3)
+                        .expect("the grid only contains holes of the board");
+                    Some(
+                        Move::from_src_dir(src, dir)
+                            .expect("the destination of a legal move is on the board"),
+                    )
+                })
+            })
+    }
+
+    /// Is this position over, i.e. are there no legal moves left?
+    pub fn is_terminal(&self) -> bool {
+        self.num_legal_moves() == 0
+    }
+
+    /// Iterate over the legal moves that jump the peg at `src`.
+    ///
+    /// This only looks at the four possible jumps from `src`, instead of
+    /// scanning the full list of [`all_moves`].
+    pub fn moves_from(&self, src: Coord) -> impl Iterator<Item = Move> + use<> {
+        let pos = *self;
+        CardinalDir::ALL
+            .into_iter()
+            .filter_map(move |dir| Move::from_src_dir(src, dir))
+            .filter(move |&mv| pos.can_move(mv))
+    }
+
+    /// Get the normalized positions that can be reached with a single move.
+    ///
+    /// Moves that lead to symmetric positions collapse into a single entry.
+    /// The result is sorted.
+    pub fn normalized_forward_neighbors(&self) -> Vec<Position> {
+        let mut neighbors: Vec<_> = self
+            .legal_moves()
+            .map(|mv| self.apply_move(mv).normalize())
+            .collect();
+        neighbors.sort_by_key(|pos| pos.0);
+        neighbors.dedup();
+        neighbors
+    }
+
+    /// Get the legal moves, keeping only one move out of each group of moves
+    /// that the symmetries of this position map onto each other.
+    ///
+    /// The skipped moves lead to positions that are symmetric to the
+    /// position after the kept move, so a search doesn't need to try them.
+    /// On the default start, this leaves one of the four opening moves.
+    pub fn symmetry_normalized_moves(&self) -> Vec<Move> {
+        let images = self.symmetric_images();
+        let stabilizer: Vec<usize> = (0..8).filter(|&i| images[i] == *self).collect();
+
+        // Keep a move if it has the smallest bits among its images.
+        self.legal_moves()
+            .filter(|mv| {
+                let removed = Position(mv.remove_bits).symmetric_images();
+                let added = Position(mv.add_bits).symmetric_images();
+                stabilizer
+                    .iter()
+                    .all(|&i| (mv.remove_bits, mv.add_bits) <= (removed[i].0, added[i].0))
+            })
+            .collect()
+    }
+
+    /// Count the legal moves that lead to a position which the filter rejects,
+    /// i.e. moves that ruin the game. The filter has no false negatives, so
+    /// every move that is counted here is a guaranteed mistake.
+    pub fn trap_severity(&self, filter: &BloomFilter) -> u32 {
+        filter
+            .query_children(*self, Direction::Forward)
+            .iter()
+            .filter(|(_, positive)| !positive)
+            .count() as u32
+    }
+
+    /// Get all moves that can be performed in this position, in random order.
+    ///
+    /// Only the legal moves are shuffled, which is cheaper than shuffling
+    /// the full list of [`all_moves`] when most of them are not applicable.
+    pub fn legal_moves_shuffled(&self, rng: &mut impl Rng) -> Vec<Move> {
+        let mut moves: Vec<_> = self.legal_moves().collect();
+        moves.shuffle(rng);
+        moves
+    }
+
+    /// Perform a uniformly random legal move, returning the move and the new
+    /// position, or `None` if there are no legal moves left.
+    pub fn apply_random_move(&self, rng: &mut impl Rng) -> Option<(Move, Position)> {
+        let mv = self.legal_moves().choose(rng)?;
+        Some((mv, self.apply_move(mv)))
+    }
+
+    /// Encode the position as a short string, suitable for URLs.
+    ///
+    /// The string is the hexadecimal representation of the bitfield, padded
+    /// to nine digits.
+    pub fn to_compact_string(&self) -> String {
+        format!("{:09x}", self.0 & ALL_HOLES_MASK)
+    }
+
+    /// Parse a string produced by [`Position::to_compact_string`].
+    ///
+    /// Returns `None` if the string is not valid hex or has bits set outside
+    /// of the board.
+    pub fn from_compact_string(s: &str) -> Option<Position> {
+        Position::new(u64::from_str_radix(s, 16).ok()?)
+    }
+
+    /// Render the position with Unicode half block characters, packing two
+    /// rows of the board into every line of text.
+    ///
+    /// Pegs are drawn as filled blocks, while empty holes and the corners
+    /// outside of the board are left blank. Every line has the full width of
+    /// the board.
+    pub fn to_compact_unicode(&self) -> String {
+        let occupied = |x, y| Coord::new(x, y).is_some_and(|c| self.is_occupied(c));
+
+        let mut out = String::new();
+        for top_y in (-3..=3).step_by(2) {
+            for x in -3..=3 {
+                out.push(match (occupied(x, top_y), occupied(x, top_y + 1)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Render the position as a TikZ picture, with filled circles for pegs
+    /// and open circles for empty holes. The output can be included in any
+    /// LaTeX document that loads the `tikz` package.
+    pub fn to_tikz(&self) -> String {
+        let mut out = String::from("\\begin{tikzpicture}\n");
+        for coord in Coord::all() {
+            let command = if self.is_occupied(coord) {
+                "fill"
+            } else {
+                "draw"
+            };
+            // TikZ y coordinates increase upwards
+            out += &format!(
+                "  \\{command} ({}, {}) circle (0.3);\n",
+                coord.x(),
+                -coord.y()
+            );
+        }
+        out += "\\end{tikzpicture}\n";
+        out
+    }
+
+    /// Run [`solve_with_bloom_filter`] and only report whether a solution
+    /// exists, see [`quick_solvability`].
+    pub fn solvable_with_filter(&self, filter: &BloomFilter, dir: Direction) -> Solvability {
+        quick_solvability(*self, filter, dir)
+    }
+
+    /// Pick a promising move by looking two moves ahead in the filter, which
+    /// is much cheaper than running the full solver.
+    ///
+    /// Among the moves that lead to a position in the filter, this picks the
+    /// one with the most children that are in the filter as well. Returns
+    /// `None` if no move leads to a position in the filter. Like
+    /// [`solve_with_bloom_filter`], the backward direction works on the
+    /// inverse position, so the move has to be undone with
+    /// [`Position::apply_move_inverse`].
+    pub fn greedy_best_move(&self, filter: &BloomFilter, dir: Direction) -> Option<Move> {
+        let pos = match dir {
+            Direction::Forward => *self,
+            Direction::Backward => self.inverse(),
+        };
+        let end = Position::default_end();
+        let is_candidate = |p: Position| p == end || filter.query(p.normalize());
+
+        pos.legal_moves()
+            .map(|mv| (mv, pos.apply_move(mv)))
+            .filter(|&(_, child)| is_candidate(child))
+            .max_by_key(|&(_, child)| {
+                let nr_candidates = child
+                    .legal_moves()
+                    .filter(|&mv| is_candidate(child.apply_move(mv)))
+                    .count();
+                // Reaching the end directly beats any number of grandchildren.
+                (child == end, nr_candidates)
+            })
+            .map(|(mv, _)| mv)
+    }
+
+    /// Generate a random solvable position with the given number of pegs.
+    /// The number of pegs is clamped to the range from 1 to [`NR_PEGS`].
+    ///
+    /// This plays random backward moves starting from the default end
+    /// position, so the result is guaranteed to be solvable. If the random
+    /// walk runs into a dead end before reaching the desired peg count, we
+    /// backtrack and try a different move.
+    ///
+    /// Close to a full board, the backtracking can take long when the early
+    /// moves were unlucky. Like the solver, we therefore start over with a
+    /// small step limit a few times before the last, unlimited attempt.
+    ///
+    /// Searching all the way back to a full board is as hard as solving the
+    /// game without a filter, so for [`NR_PEGS`] we directly return the
+    /// default start position.
+    pub fn random_solvable(nr_pegs: i32, seed: u64) -> Position {
+        let nr_pegs = nr_pegs.clamp(1, NR_PEGS as i32);
+        if nr_pegs == NR_PEGS as i32 {
+            return Position::default_start();
+        }
+
+        /// Returns `None` if the peg count can't be reached from `pos`, or
+        /// if the step limit was exceeded.
+        fn depth_first_search(
+            pos: Position,
+            nr_pegs: i32,
+            rng: &mut Pcg64Mcg,
+            dead_ends: &mut FxHashSet<Position>,
+            nr_steps: &mut u32,
+            step_limit: u32,
+        ) -> Option<Position> {
+            if pos.count() == nr_pegs {
+                return Some(pos);
+            }
+            if *nr_steps >= step_limit {
+                return None;
+            }
+            *nr_steps += 1;
+
+            let mut moves = all_moves();
+            moves.shuffle(rng);
+            for mv in moves {
+                if !pos.can_move_inverse(mv) {
+                    continue;
+                }
+                let next = pos.apply_move_inverse(mv);
+                if dead_ends.contains(&next) {
+                    continue;
+                }
+                let found = depth_first_search(next, nr_pegs, rng, dead_ends, nr_steps, step_limit);
+                if found.is_some() || *nr_steps >= step_limit {
+                    return found;
+                }
+                dead_ends.insert(next);
+            }
+
+            None
+        }
+
+        let mut rng = Pcg64Mcg::seed_from_u64(seed);
+        let nr_attempts = RANDOM_SOLVABLE_ATTEMPTS;
+        for attempt in 0..nr_attempts {
+            let last_attempt = attempt + 1 == nr_attempts;
+            let step_limit = if last_attempt {
+                u32::MAX
+            } else {
+                RANDOM_SOLVABLE_STEP_LIMIT
+            };
+
+            let found = depth_first_search(
+                Position::default_end(),
+                nr_pegs,
+                &mut rng,
+                &mut FxHashSet::default(),
+                &mut 0,
+                step_limit,
+            );
+            if let Some(pos) = found {
+                pos.debug_assert_no_stray_bits();
+                return pos;
+            }
+        }
+        unreachable!("the default start position is reachable from the end")
+    }
+
+    /// Play up to `max_steps` random legal moves starting from `start`,
+    /// stopping early if no more moves are possible.
+    ///
+    /// This is the forward counterpart to [`Position::random_solvable`]: the
+    /// result is guaranteed to be reachable from `start`, but not necessarily
+    /// solvable.
+    pub fn random_reachable_from(start: Position, rng: &mut impl Rng, max_steps: u32) -> Position {
+        let mut pos = start;
+        for _ in 0..max_steps {
+            match pos.apply_random_move(rng) {
+                Some((_, next)) => pos = next,
+                None => break,
+            }
+        }
+        pos
+    }
 }
 
 impl Display for Position {
@@ -284,6 +1051,76 @@ impl Display for Position {
     }
 }
 
+/// Version of the [`BloomFilter`] file format. This has to be incremented
+/// whenever the encoding or the hashing changes, so that old files are
+/// rejected instead of being misread.
+///
+/// Files written before the version header was introduced count as version
+/// 0 and are still accepted, since the encoding hasn't changed since then.
+pub const BLOOM_FILTER_FORMAT_VERSION: u16 = 1;
+
+/// Version of the [`BlockedBloomFilter`] file format, see
+/// [`BLOOM_FILTER_FORMAT_VERSION`].
+pub const BLOCKED_BLOOM_FILTER_FORMAT_VERSION: u16 = 1;
+
+/// Version of the [`HybridFilter`] file format, see
+/// [`BLOOM_FILTER_FORMAT_VERSION`].
+pub const HYBRID_FILTER_FORMAT_VERSION: u16 = 1;
+
+/// Decode a filter that is prefixed with the given format version, or an
+/// unversioned filter from before the header was introduced.
+///
+/// The two can be told apart because the version header shifts the fields,
+/// so reading a versioned file without the header gives an invalid `k`, and
+/// vice versa.
+fn decode_versioned_filter<T: bincode::Decode<()>>(
+    data: &[u8],
+    expected_version: u16,
+    name: &str,
+    is_valid: impl Fn(&T) -> bool,
+) -> Result<T, bincode::error::DecodeError> {
+    let (version, header_len) = bincode::decode_from_slice::<u16, _>(data, bincode_config())?;
+    if version == expected_version
+        && let Ok((filter, _)) =
+            bincode::decode_from_slice::<T, _>(&data[header_len..], bincode_config())
+        && is_valid(&filter)
+    {
+        return Ok(filter);
+    }
+
+    if let Ok((filter, len)) = bincode::decode_from_slice::<T, _>(data, bincode_config())
+        && len == data.len()
+        && is_valid(&filter)
+    {
+        return Ok(filter);
+    }
+
+    Err(bincode::error::DecodeError::OtherString(format!(
+        "unsupported {name} format version {version}, expected {expected_version}"
+    )))
+}
+
+/// Fill ratio above which [`BloomFilter::insert_all`] warns that the filter
+/// is too small for the inserted set.
+pub const FILL_RATIO_WARNING_THRESHOLD: f64 = 0.6;
+
+/// Returned by [`BloomFilter::insert_all`] when the filter ended up so full
+/// that a large part of all queries are false positives.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverfullFilter {
+    pub fill_ratio: f64,
+}
+
+impl Display for OverfullFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "bloom filter is {:.1}% full, it is probably too small for the inserted positions",
+            self.fill_ratio * 100.0
+        )
+    }
+}
+
 #[cfg_attr(not(target_family = "wasm"), derive(bincode::Encode))]
 #[derive(bincode::Decode)]
 pub struct BloomFilter {
@@ -327,6 +1164,23 @@ impl BloomFilter {
         self.nr_bits
     }
 
+    /// Number of bits needed so that a filter with `nr_elements` inserted
+    /// positions and `k` hashes per position has a false positive rate of
+    /// at most `target_fpr`, assuming the hashes are uniform.
+    ///
+    /// This solves `fpr = (1 - e^(-k * n / m))^k` for the size `m`.
+    pub fn recommended_size(nr_elements: u64, target_fpr: f64, k: u32) -> u32 {
+        assert!(
+            0.0 < target_fpr && target_fpr < 1.0,
+            "the false positive rate has to be between 0 and 1"
+        );
+        assert!(k > 0, "need at least one hash");
+
+        let k = k as f64;
+        let nr_bits = -k * nr_elements as f64 / (1.0 - target_fpr.powf(1.0 / k)).ln();
+        nr_bits.ceil() as u32
+    }
+
     fn hash(&self, pos: Position) -> usize {
         let nr_bits = self.nr_bits() as u64;
         (pos.0 % nr_bits) as usize
@@ -337,6 +1191,20 @@ impl BloomFilter {
         self.bits.0.set(hash, true);
     }
 
+    /// Insert all given positions, and check afterwards that the filter
+    /// isn't filled above [`FILL_RATIO_WARNING_THRESHOLD`].
+    pub fn insert_all(
+        &mut self,
+        positions: impl IntoIterator<Item = Position>,
+    ) -> Option<OverfullFilter> {
+        for position in positions {
+            self.insert(position);
+        }
+
+        let fill_ratio = self.fill_ratio();
+        (fill_ratio > FILL_RATIO_WARNING_THRESHOLD).then_some(OverfullFilter { fill_ratio })
+    }
+
     /// Check if a value is present in the filter.
     ///
     /// This may return false positives, but never false negatives.
@@ -345,20 +1213,73 @@ impl BloomFilter {
         *self.bits.0.get(hash).unwrap()
     }
 
+    /// Fraction of the bits that are set. With `k = 1` this is also the
+    /// probability that a query for a position outside of the inserted set
+    /// is a false positive.
+    pub fn fill_ratio(&self) -> f64 {
+        self.bits.0[..self.nr_bits as usize].count_ones() as f64 / self.nr_bits as f64
+    }
+
+    /// Query the filter for every position that can be reached with a single
+    /// move in the given direction.
+    ///
+    /// Returns the legal moves along with the query result for the
+    /// normalized position after the move.
+    pub fn query_children(&self, pos: Position, dir: Direction) -> Vec<(Move, bool)> {
+        ALL_MOVES
+            .iter()
+            .filter_map(|&mv| {
+                let child = match dir {
+                    Direction::Forward => pos.can_move(mv).then(|| pos.apply_move(mv)),
+                    Direction::Backward => {
+                        pos.can_move_inverse(mv).then(|| pos.apply_move_inverse(mv))
+                    }
+                }?;
+                Some((mv, self.query(child.normalize())))
+            })
+            .collect()
+    }
+
     fn check_valid_k(&self) {
         assert_eq!(self.k, 1, "only k=1 supported currently");
     }
 
-    pub fn load_from_slice(data: &[u8]) -> Self {
-        let (filter, _) =
-            bincode::decode_from_slice::<BloomFilter, _>(data, bincode_config()).unwrap();
-        filter.check_valid_k();
-        filter
+    /// Load a filter that was saved with [`BloomFilter::save_to_file`].
+    ///
+    /// Fails if the data was written with a different version of the format.
+    /// Unversioned files from before the header was introduced are accepted.
+    pub fn load_from_slice(data: &[u8]) -> Result<Self, bincode::error::DecodeError> {
+        decode_versioned_filter(
+            data,
+            BLOOM_FILTER_FORMAT_VERSION,
+            "bloom filter",
+            BloomFilter::is_valid,
+        )
     }
 
-    #[cfg(test)]
-    /// Generate a Bloom Filter that returns true on every query.
-    fn always_true() -> Self {
+    /// Check that the decoded fields are consistent with each other.
+    fn is_valid(&self) -> bool {
+        self.k == 1 && self.bits.0.len() >= self.nr_bits as usize
+    }
+
+    /// Generate a Bloom Filter that returns true on every query.
+    ///
+    /// The solver can't prune anything with this filter, so it only finishes
+    /// in time for positions with few pegs. This is useful for tests that
+    /// don't want to ship a real filter.
+    ///
+    /// ```
+    /// use common::{BloomFilter, Direction, Position, SolveResult, solve_with_bloom_filter};
+    ///
+    /// let pos = Position::random_solvable(5, 0);
+    /// let filter = BloomFilter::accept_all();
+    /// let (result, _) = solve_with_bloom_filter(pos, &filter, Direction::Forward, 0);
+    /// let SolveResult::Solved(moves) = result else {
+    ///     panic!("a solvable position with 5 pegs should be solved");
+    /// };
+    /// assert_eq!(moves.len(), 4);
+    /// ```
+    pub fn accept_all() -> Self {
         Self {
             nr_bits: 1,
             k: 1,
@@ -369,14 +1290,236 @@ impl BloomFilter {
 
 #[cfg(not(target_family = "wasm"))]
 impl BloomFilter {
+    /// Save the filter, prefixed with the format version.
+    pub fn save_to_file(&self, path: impl AsRef<Path>) {
+        let mut file = std::fs::File::create(path).unwrap();
+        bincode::encode_into_std_write(BLOOM_FILTER_FORMAT_VERSION, &mut file, bincode_config())
+            .unwrap();
+        bincode::encode_into_std_write(self, &mut file, bincode_config()).unwrap();
+    }
+
+    /// See [`BloomFilter::load_from_slice`].
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self, bincode::error::DecodeError> {
+        let data = std::fs::read(path).map_err(|inner| bincode::error::DecodeError::Io {
+            inner,
+            additional: 0,
+        })?;
+        Self::load_from_slice(&data)
+    }
+}
+
+/// Number of bits in one block of a [`BlockedBloomFilter`], matching the size
+/// of a typical 64 byte cache line.
+const BLOCK_BITS: u32 = 512;
+
+/// Maximum `k` supported by the [`BlockedBloomFilter`]. Every bit index
+/// within a block consumes 9 bits of a single 64 bit hash.
+const MAX_BLOCKED_K: u32 = 7;
+
+/// A bloom filter where all `k` bits belonging to a position lie within the
+/// same block of [`BLOCK_BITS`] bits. Compared to the flat [`BloomFilter`],
+/// a query touches only a single cache line, at the cost of a slightly
+/// higher false positive rate for the same size.
+#[cfg_attr(not(target_family = "wasm"), derive(bincode::Encode))]
+#[derive(bincode::Decode)]
+pub struct BlockedBloomFilter {
+    nr_blocks: u32,
+    k: u32,
+    bits: BincodeBitBox,
+}
+
+impl Debug for BlockedBloomFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlockedBloomFilter")
+            .field("nr_blocks", &self.nr_blocks)
+            .field("k", &self.k)
+            .finish()
+    }
+}
+
+impl Eq for BlockedBloomFilter {}
+
+impl PartialEq for BlockedBloomFilter {
+    fn eq(&self, other: &Self) -> bool {
+        self.nr_blocks == other.nr_blocks && self.k == other.k && self.bits.0 == other.bits.0
+    }
+}
+
+impl BlockedBloomFilter {
+    /// Create an empty filter. The size is rounded up to a whole number of
+    /// blocks.
+    pub fn new(nr_bits: u32, k: u32) -> Self {
+        let nr_blocks = nr_bits.div_ceil(BLOCK_BITS).max(1);
+        let filter = Self {
+            nr_blocks,
+            k,
+            bits: BincodeBitBox(bitbox![u32, Lsb0; 0; (nr_blocks * BLOCK_BITS) as usize]),
+        };
+        filter.check_valid_k();
+        filter
+    }
+
+    /// The size of the bloom filter in bits
+    pub fn nr_bits(&self) -> u32 {
+        self.nr_blocks * BLOCK_BITS
+    }
+
+    /// Get the bit indices for the given position. All of them lie within
+    /// the same block.
+    fn hashes(&self, pos: Position) -> impl Iterator<Item = usize> + use<> {
+        let block_start = (pos.0 % self.nr_blocks as u64) as usize * BLOCK_BITS as usize;
+        // Fibonacci hashing to spread the bits within the block
+        let hash = pos.0.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        (0..self.k).map(move |i| block_start + ((hash >> (64 - 9 * (i + 1))) & 511) as usize)
+    }
+
+    pub fn insert(&mut self, position: Position) {
+        for hash in self.hashes(position) {
+            self.bits.0.set(hash, true);
+        }
+    }
+
+    /// Check if a value is present in the filter.
+    ///
+    /// This may return false positives, but never false negatives.
+    pub fn query(&self, position: Position) -> bool {
+        self.hashes(position).all(|hash| self.bits.0[hash])
+    }
+
+    fn check_valid_k(&self) {
+        assert!(
+            (1..=MAX_BLOCKED_K).contains(&self.k),
+            "k must be between 1 and {MAX_BLOCKED_K}"
+        );
+    }
+
+    /// Load a filter that was saved with [`BlockedBloomFilter::save_to_file`].
+    ///
+    /// Fails if the data was written with a different version of the format.
+    /// Unversioned files from before the header was introduced are accepted.
+    pub fn load_from_slice(data: &[u8]) -> Result<Self, bincode::error::DecodeError> {
+        decode_versioned_filter(
+            data,
+            BLOCKED_BLOOM_FILTER_FORMAT_VERSION,
+            "blocked bloom filter",
+            |filter: &BlockedBloomFilter| {
+                (1..=MAX_BLOCKED_K).contains(&filter.k)
+                    && filter.bits.0.len() as u64 == filter.nr_blocks as u64 * BLOCK_BITS as u64
+            },
+        )
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl BlockedBloomFilter {
+    /// Save the filter, prefixed with the format version.
+    pub fn save_to_file(&self, path: impl AsRef<Path>) {
+        let mut file = std::fs::File::create(path).unwrap();
+        bincode::encode_into_std_write(
+            BLOCKED_BLOOM_FILTER_FORMAT_VERSION,
+            &mut file,
+            bincode_config(),
+        )
+        .unwrap();
+        bincode::encode_into_std_write(self, &mut file, bincode_config()).unwrap();
+    }
+
+    /// See [`BlockedBloomFilter::load_from_slice`].
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self, bincode::error::DecodeError> {
+        let data = std::fs::read(path).map_err(|inner| bincode::error::DecodeError::Io {
+            inner,
+            additional: 0,
+        })?;
+        Self::load_from_slice(&data)
+    }
+}
+
+/// A filter that stores positions with few pegs exactly and falls back to a
+/// [`BloomFilter`] for all other positions.
+///
+/// Close to the end of the game there are only few solvable positions, so
+/// they can be stored exactly. This removes the false positives in the part
+/// of the search where they hurt the pruning the most.
+#[cfg_attr(not(target_family = "wasm"), derive(bincode::Encode))]
+#[derive(bincode::Decode, Debug)]
+pub struct HybridFilter {
+    max_exact_pegs: i32,
+    /// The raw bits of the positions, since `Position` can't be encoded.
+    exact: FxHashSet<u64>,
+    bloom_filter: BloomFilter,
+}
+
+impl HybridFilter {
+    /// Create a filter that stores positions with at most `max_exact_pegs`
+    /// pegs exactly, and all other positions in the given bloom filter.
+    pub fn new(bloom_filter: BloomFilter, max_exact_pegs: i32) -> Self {
+        Self {
+            max_exact_pegs,
+            exact: FxHashSet::default(),
+            bloom_filter,
+        }
+    }
+
+    fn is_exact(&self, position: Position) -> bool {
+        position.count() <= self.max_exact_pegs
+    }
+
+    pub fn insert(&mut self, position: Position) {
+        if self.is_exact(position) {
+            self.exact.insert(position.0);
+        } else {
+            self.bloom_filter.insert(position);
+        }
+    }
+
+    /// Check if a value is present in the filter.
+    ///
+    /// This never returns false negatives, and only returns false positives
+    /// for positions with more than `max_exact_pegs` pegs.
+    pub fn query(&self, position: Position) -> bool {
+        if self.is_exact(position) {
+            self.exact.contains(&position.0)
+        } else {
+            self.bloom_filter.query(position)
+        }
+    }
+
+    /// Load a filter that was saved with [`HybridFilter::save_to_file`].
+    ///
+    /// Fails if the data was written with a different version of the format.
+    pub fn load_from_slice(data: &[u8]) -> Result<Self, bincode::error::DecodeError> {
+        decode_versioned_filter(
+            data,
+            HYBRID_FILTER_FORMAT_VERSION,
+            "hybrid filter",
+            |filter: &HybridFilter| {
+                filter.bloom_filter.is_valid()
+                    && filter
+                        .exact
+                        .iter()
+                        .all(|&bits| Position(bits).count() <= filter.max_exact_pegs)
+            },
+        )
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl HybridFilter {
+    /// Save the filter, prefixed with the format version.
     pub fn save_to_file(&self, path: impl AsRef<Path>) {
         let mut file = std::fs::File::create(path).unwrap();
+        bincode::encode_into_std_write(HYBRID_FILTER_FORMAT_VERSION, &mut file, bincode_config())
+            .unwrap();
         bincode::encode_into_std_write(self, &mut file, bincode_config()).unwrap();
     }
 
-    pub fn load_from_file(path: impl AsRef<Path>) -> Self {
-        let mut file = std::fs::File::open(path).unwrap();
-        bincode::decode_from_std_read(&mut file, bincode_config()).unwrap()
+    /// See [`HybridFilter::load_from_slice`].
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self, bincode::error::DecodeError> {
+        let data = std::fs::read(path).map_err(|inner| bincode::error::DecodeError::Io {
+            inner,
+            additional: 0,
+        })?;
+        Self::load_from_slice(&data)
     }
 }
 
@@ -448,10 +1591,144 @@ pub enum SolveResult {
     TimedOut,
 }
 
+impl SolveResult {
+    /// The sequence of positions visited when playing the solution forward
+    /// from `start`, including `start` itself and the final position.
+    ///
+    /// For a backward search, `start` has to be the inverse of the position
+    /// that was passed to the solver. Returns `None` if there is no solution.
+    pub fn positions(&self, start: Position) -> Option<Vec<Position>> {
+        let SolveResult::Solved(moves) = self else {
+            return None;
+        };
+        let mut positions = Vec::with_capacity(moves.len() + 1);
+        positions.push(start);
+        let mut pos = start;
+        for &mv in moves {
+            pos = pos.apply_move(mv);
+            positions.push(pos);
+        }
+        Some(positions)
+    }
+
+    /// The solution together with the position it starts from. See
+    /// [`SolveResult::positions`] for the meaning of `start`. Returns `None`
+    /// if there is no solution.
+    pub fn solution(&self, start: Position) -> Option<Solution> {
+        let SolveResult::Solved(moves) = self else {
+            return None;
+        };
+        Some(Solution {
+            start,
+            moves: moves.clone(),
+        })
+    }
+}
+
+/// Is a position solvable, i.e. does a path exist from the position to the
+/// end? This is the summary of a [`SolveResult`] shown in the frontend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Solvability {
+    /// Yes, the position is solvable.
+    Solvable,
+    /// Yes, we have already reached the target position.
+    Solved,
+    /// No, the position is not solvable.
+    Unsolvable,
+    /// Maybe. We haven't run the solver for this position yet.
+    Unknown,
+    /// Maybe. The solver gave up before it found a solution or proved that
+    /// there is none.
+    TimedOut,
+}
+
+impl From<&SolveResult> for Solvability {
+    fn from(result: &SolveResult) -> Self {
+        match result {
+            SolveResult::Solved(moves) if moves.is_empty() => Solvability::Solved,
+            SolveResult::Solved(_) => Solvability::Solvable,
+            SolveResult::Unsolvable => Solvability::Unsolvable,
+            SolveResult::TimedOut => Solvability::TimedOut,
+        }
+    }
+}
+
+impl Solvability {
+    /// Check if the position is either solvable or already solved.
+    pub fn solvable(self) -> bool {
+        match self {
+            Solvability::Solvable => true,
+            Solvability::Solved => true,
+            Solvability::Unsolvable => false,
+            Solvability::Unknown => false,
+            Solvability::TimedOut => false,
+        }
+    }
+}
+
+/// A sequence of moves along with the position that they are played from.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Solution {
+    pub start: Position,
+    pub moves: Vec<Move>,
+}
+
+impl Solution {
+    /// Iterate over the steps of the solution as triples of the position
+    /// before the move, the move, and the position after the move.
+    pub fn iter_steps(&self) -> impl Iterator<Item = (Position, Move, Position)> + use<'_> {
+        self.moves.iter().scan(self.start, |pos, &mv| {
+            let before = *pos;
+            *pos = before.apply_move(mv);
+            Some((before, mv, *pos))
+        })
+    }
+
+    /// The position after all moves have been played.
+    pub fn final_position(&self) -> Position {
+        self.moves
+            .iter()
+            .fold(self.start, |pos, &mv| pos.apply_move(mv))
+    }
+}
+
+impl<'a> IntoIterator for &'a Solution {
+    type Item = (Position, Move, Position);
+    type IntoIter = Box<dyn Iterator<Item = Self::Item> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter_steps())
+    }
+}
+
+/// Maximum number of positions that [`count_solutions`] expands before it
+/// gives up.
+const COUNT_SOLUTIONS_STEP_LIMIT: u32 = 10000;
+
+/// Settings that change how the solver picks between moves.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct SolveOptions {
+    /// Try the moves that lead to the most symmetric positions first, see
+    /// [`Position::symmetry_count`]. This tends to find more symmetric
+    /// solutions, but doesn't guarantee the most symmetric one.
+    pub prefer_symmetric: bool,
+    /// Give up with [`SolveResult::TimedOut`] once the attempts together
+    /// have expanded this many positions, instead of only limiting each
+    /// attempt on its own. This bounds the time the solver can take.
+    pub max_total_steps: Option<u32>,
+}
+
 /// Additional statistics about the solve process
+#[derive(Default)]
 pub struct SolveInfo {
     pub nr_steps: u32,
     pub nr_attempts: u32,
+    /// Number of filter queries during the search that returned true. Some
+    /// of these are false positives that lead to wasted search.
+    pub nr_filter_hits: u32,
+    /// Number of filter queries during the search that returned false, i.e.
+    /// branches that were pruned.
+    pub nr_filter_misses: u32,
 }
 
 /// Find a path from the given position to the default end position using DFS
@@ -459,33 +1736,95 @@ pub struct SolveInfo {
 /// If the direction is set to backward, then we search a path to the start
 /// instead, i.e. solving the problem in reverse.
 pub fn solve_with_bloom_filter(
+    pos: Position,
+    filter: &BloomFilter,
+    dir: Direction,
+    seed: u64,
+) -> (SolveResult, SolveInfo) {
+    solve_to_with_bloom_filter(pos, Position::default_end(), filter, dir, seed)
+}
+
+/// Run the solver once and only report whether the position is solvable,
+/// for one-off queries that don't need the path.
+pub fn quick_solvability(pos: Position, filter: &BloomFilter, dir: Direction) -> Solvability {
+    Solvability::from(&solve_with_bloom_filter(pos, filter, dir, 0).0)
+}
+
+/// Find a position that differs from `pos` in a single hole and passes both
+/// the filter and the de Bruijn check, to suggest how an unsolvable position
+/// can be made solvable again. Removing a peg is preferred over adding one.
+///
+/// The filter can have false positives, so the result is not guaranteed to
+/// be solvable. Returns `None` if no single toggle passes both checks.
+pub fn nearest_solvable(pos: Position, filter: &BloomFilter) -> Option<Position> {
+    let (pegs, holes): (Vec<Coord>, Vec<Coord>) =
+        Coord::ALL.into_iter().partition(|&c| pos.is_occupied(c));
+    pegs.into_iter()
+        .chain(holes)
+        .map(|c| Position(pos.0 ^ c.bitmask()))
+        .find(|&candidate| de_bruijn_solvable(candidate) && filter.query(candidate.normalize()))
+}
+
+/// Like [`solve_with_bloom_filter`], but search a path to a custom end
+/// position. The filter has to contain the positions that can reach `end`.
+///
+/// In the backward direction, the search runs from the inverse of `pos`, so
+/// the path leads to the inverse of `end`.
+///
+/// The filter is only queried with normalized positions if `end` is mapped
+/// onto itself by every symmetry of the board, like the default end. For any
+/// other end, a normalized position can stand for an image that reaches a
+/// different end, so the filter is queried with the positions as they are.
+pub fn solve_to_with_bloom_filter(
+    pos: Position,
+    end: Position,
+    filter: &BloomFilter,
+    dir: Direction,
+    seed: u64,
+) -> (SolveResult, SolveInfo) {
+    solve_to_with_options(pos, end, filter, dir, seed, SolveOptions::default())
+}
+
+/// Like [`solve_to_with_bloom_filter`], with additional [`SolveOptions`].
+pub fn solve_to_with_options(
     mut pos: Position,
+    end: Position,
     filter: &BloomFilter,
     dir: Direction,
     seed: u64,
+    options: SolveOptions,
 ) -> (SolveResult, SolveInfo) {
-    let mut solve_info = SolveInfo {
-        nr_steps: 0,
-        nr_attempts: 0,
-    };
-    if !de_bruijn_solvable(pos) {
+    let mut solve_info = SolveInfo::default();
+    if de_bruijn_class(pos) != de_bruijn_class(end) {
         return (SolveResult::Unsolvable, solve_info);
     }
 
     fn depth_first_search(
         pos: Position,
-        filter: &BloomFilter,
+        in_filter: &dyn Fn(Position) -> bool,
         end: Position,
-        nr_steps: &mut u32,
+        solve_info: &mut SolveInfo,
         moves: &[Move; 76],
         step_limit: u32,
+        options: SolveOptions,
     ) -> SolveResult {
-        if *nr_steps > step_limit {
+        if solve_info.nr_steps > step_limit {
             return SolveResult::TimedOut;
         }
-        *nr_steps += 1;
+        solve_info.nr_steps += 1;
+
+        let mut preferred_order = vec![];
+        let order: &[Move] = if options.prefer_symmetric {
+            preferred_order.extend(moves.iter().copied().filter(|&mv| pos.can_move(mv)));
+            // The sort is stable, so equally symmetric moves keep their
+            // shuffled order.
+            preferred_order.sort_by_key(|&mv| Reverse(pos.apply_move(mv).symmetry_count()));
+            &preferred_order
+        } else {
+            moves
+        };
 
-        for &mv in moves {
+        for &mv in order {
             if pos.can_move(mv) {
                 let next = pos.apply_move(mv);
 
@@ -494,17 +1833,24 @@ pub fn solve_with_bloom_filter(
                     return SolveResult::Solved(vec![mv]);
                 }
 
-                // If the next position only has a single peg left somewhere
-                // other than in the end position then we skip it.
-                if next.count() == 1 {
+                // If the next position has no more pegs than the end
+                // position, but is not the end position, then it can't
+                // lead to the end and we skip it.
+                if next.count() <= end.count() {
                     continue;
                 }
 
-                if !filter.query(next.normalize()) {
+                if in_filter(next) {
+                    solve_info.nr_filter_hits += 1;
+                } else {
+                    solve_info.nr_filter_misses += 1;
                     continue;
                 }
 
-                match depth_first_search(next, filter, end, nr_steps, moves, step_limit) {
+                let result = depth_first_search(
+                    next, in_filter, end, solve_info, moves, step_limit, options,
+                );
+                match result {
                     SolveResult::Solved(mut list) => {
                         list.push(mv);
                         return SolveResult::Solved(list);
@@ -518,7 +1864,16 @@ pub fn solve_with_bloom_filter(
         SolveResult::Unsolvable
     }
 
-    if !filter.query(pos.normalize()) {
+    let normalize = end.symmetry_count() == 8;
+    let in_filter = |pos: Position| {
+        if normalize {
+            filter.query(pos.normalize())
+        } else {
+            filter.query(pos)
+        }
+    };
+
+    if !in_filter(pos) {
         return (SolveResult::Unsolvable, solve_info);
     }
 
@@ -529,7 +1884,6 @@ pub fn solve_with_bloom_filter(
         pos = pos.inverse();
     }
 
-    let end = Position::default_end();
     if pos == end {
         return (SolveResult::Solved(vec![]), solve_info);
     }
@@ -542,10 +1896,30 @@ pub fn solve_with_bloom_filter(
             step_limit = 10000;
         }
 
-        let mut nr_steps = 0;
-        let result = depth_first_search(pos, filter, end, &mut nr_steps, &moves, step_limit);
-        solve_info.nr_steps += nr_steps;
+        // An attempt expands at most one position more than its limit.
+        let mut attempt_limit = step_limit;
+        if let Some(max_total_steps) = options.max_total_steps {
+            let remaining = max_total_steps.saturating_sub(solve_info.nr_steps);
+            if remaining == 0 {
+                break;
+            }
+            attempt_limit = attempt_limit.min(remaining - 1);
+        }
+
+        let mut attempt_info = SolveInfo::default();
+        let result = depth_first_search(
+            pos,
+            &in_filter,
+            end,
+            &mut attempt_info,
+            &moves,
+            attempt_limit,
+            options,
+        );
+        solve_info.nr_steps += attempt_info.nr_steps;
         solve_info.nr_attempts += 1;
+        solve_info.nr_filter_hits += attempt_info.nr_filter_hits;
+        solve_info.nr_filter_misses += attempt_info.nr_filter_misses;
 
         match result {
             SolveResult::Solved(mut list) => {
@@ -562,89 +1936,870 @@ pub fn solve_with_bloom_filter(
     (SolveResult::TimedOut, solve_info)
 }
 
-/// A list of all possible moves on a peg solitaire board.
+/// Number of positions that a single attempt of [`Position::random_solvable`]
+/// expands before it starts over, except for the last attempt.
+const RANDOM_SOLVABLE_STEP_LIMIT: u32 = 2000;
+/// Number of attempts of [`Position::random_solvable`], including the last
+/// attempt without a step limit.
+const RANDOM_SOLVABLE_ATTEMPTS: u32 = 20;
+
+/// Estimate how likely it is to get from `start` to `end` by playing
+/// uniformly random moves until no move is left.
 ///
-/// This list does not take a current board position into account, therefore
-/// for a given board position only some of these moves will be applicable
-/// in this moment.
-pub fn all_moves() -> [Move; 76] {
-    let mut all = Vec::new();
-
-    for direction in 0..4 {
-        let moves_in_this_direction = Coord::all().into_iter().filter_map(|coord| {
-            let mut coord_a = coord;
-            let mut coord_b = coord_a.shift(1, 0)?;
-            let mut coord_c = coord_a.shift(2, 0)?;
-
-            for _ in 0..direction {
-                coord_a = coord_a.rotate();
-                coord_b = coord_b.rotate();
-                coord_c = coord_c.rotate();
-            }
-
-            let remove_bits = coord_a.bitmask() | coord_b.bitmask();
-            let add_bits = coord_c.bitmask();
-            let mv = Move {
-                remove_bits,
-                add_bits,
-                src: coord_a,
-                dst: coord_c,
-                middle: coord_b,
-            };
-            Some(mv)
-        });
+/// Returns the fraction of the `trials` random games that ended in `end`. The
+/// result only depends on the state of `rng`, so a seeded rng gives
+/// reproducible estimates.
+pub fn estimate_random_success_rate(
+    start: Position,
+    end: Position,
+    trials: u32,
+    rng: &mut impl Rng,
+) -> f64 {
+    assert!(trials > 0, "need at least one trial");
+    let mut nr_successes = 0;
+    for _ in 0..trials {
+        let mut pos = start;
+        while let Some((_, next)) = pos.apply_random_move(rng) {
+            pos = next;
+        }
+        if pos == end {
+            nr_successes += 1;
+        }
+    }
+    nr_successes as f64 / trials as f64
+}
+
+/// Find a path from `pos` to `end` with an exhaustive DFS that doesn't rely on
+/// a filter, so the result is never wrong and never times out.
+///
+/// The search remembers every dead end it has seen, but it is still far too
+/// slow for the full board. It is meant for checking the filter based solver
+/// on positions that are close to the end. The direction is handled like in
+/// [`solve_to_with_bloom_filter`].
+pub fn solve_exact(mut pos: Position, end: Position, dir: Direction) -> SolveResult {
+    fn depth_first_search(
+        pos: Position,
+        end: Position,
+        dead_ends: &mut FxHashSet<Position>,
+    ) -> Option<Vec<Move>> {
+        if pos == end {
+            return Some(vec![]);
+        }
+        if pos.count() <= end.count() || dead_ends.contains(&pos) {
+            return None;
+        }
 
-        all.extend(moves_in_this_direction);
+        for mv in pos.legal_moves() {
+            if let Some(mut list) = depth_first_search(pos.apply_move(mv), end, dead_ends) {
+                list.push(mv);
+                return Some(list);
+            }
+        }
+
+        dead_ends.insert(pos);
+        None
     }
 
-    all.try_into().expect("should find exactly 76 moves")
+    if dir == Direction::Backward {
+        pos = pos.inverse();
+    }
+
+    match depth_first_search(pos, end, &mut FxHashSet::default()) {
+        Some(mut list) => {
+            list.reverse();
+            SolveResult::Solved(list)
+        }
+        None => SolveResult::Unsolvable,
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use proptest::proptest;
-    use rand::{RngCore, SeedableRng};
-    use tempfile::tempdir;
+/// Result of [`count_solutions`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SolutionCount {
+    /// The number of distinct move sequences that reach the end.
+    Exact(u64),
+    /// There are at least this many solutions, either because the count
+    /// reached the cap or because the search ran out of steps.
+    AtLeast(u64),
+}
 
-    use crate::coord::Coord;
+/// Count the distinct move sequences from `pos` to `end`, stopping once
+/// `cap` solutions have been found.
+///
+/// Positions that are not in the filter are pruned like in
+/// [`solve_to_with_bloom_filter`], which never removes a solution since the
+/// filter has no false negatives. Counts of positions that have already been
+/// visited are reused, so the step limit bounds the number of distinct
+/// positions that are expanded. The direction is handled like in
+/// [`solve_to_with_bloom_filter`].
+pub fn count_solutions(
+    mut pos: Position,
+    end: Position,
+    filter: &BloomFilter,
+    dir: Direction,
+    cap: u64,
+) -> SolutionCount {
+    /// Returns the number of solutions, capped at `cap`, and whether the
+    /// subtree was searched completely.
+    fn count(
+        pos: Position,
+        end: Position,
+        filter: &BloomFilter,
+        cap: u64,
+        counts: &mut FxHashMap<Position, u64>,
+        nr_steps: &mut u32,
+    ) -> (u64, bool) {
+        if pos == end {
+            return (1, true);
+        }
+        if let Some(&known) = counts.get(&pos) {
+            return (known, true);
+        }
+        if *nr_steps >= COUNT_SOLUTIONS_STEP_LIMIT {
+            return (0, false);
+        }
+        *nr_steps += 1;
 
-    use super::*;
+        let mut total = 0;
+        for mv in pos.legal_moves() {
+            let next = pos.apply_move(mv);
+            if next != end && (next.count() <= end.count() || !filter.query(next.normalize())) {
+                continue;
+            }
 
-    fn position_from_ascii_multiline(text: &str) -> Position {
-        let lines = text
-            .lines()
-            .filter(|line| !line.trim().is_empty())
-            .collect::<Vec<_>>()
-            .try_into()
-            .unwrap();
-        Position::from_ascii(lines)
+            let (nr_solutions, complete) = count(next, end, filter, cap, counts, nr_steps);
+            total = (total + nr_solutions).min(cap);
+            if !complete {
+                return (total, false);
+            }
+            if total == cap {
+                break;
+            }
+        }
+
+        counts.insert(pos, total);
+        (total, true)
     }
 
-    #[test]
-    // test if the coordinate bits appear in the expected sequential order
-    fn test_coords() {
-        let mut next_mask = 1;
-        for coord in Coord::all() {
-            assert_eq!(next_mask, coord.bitmask());
-            next_mask *= 2;
-        }
+    if dir == Direction::Backward {
+        pos = pos.inverse();
+    }
+    if de_bruijn_class(pos) != de_bruijn_class(end) {
+        return SolutionCount::Exact(0);
+    }
 
-        assert_eq!(next_mask, 1u64 << 33);
+    let (total, complete) = count(pos, end, filter, cap, &mut FxHashMap::default(), &mut 0);
+    if complete && total < cap {
+        SolutionCount::Exact(total)
+    } else {
+        SolutionCount::AtLeast(total)
     }
+}
 
-    #[test]
-    fn test_move_list_contains_all_unique_moves() {
-        let moves = all_moves();
+/// Turn a forward solution into the sequence of moves that plays it back from
+/// the end to the start.
+///
+/// The returned moves are meant to be applied with
+/// [`Position::apply_move_inverse`], i.e. every move is undone, starting with
+/// the last move of the forward solution.
+pub fn reverse_solution(moves: &[Move]) -> Vec<Move> {
+    moves.iter().rev().copied().collect()
+}
 
-        for i in 0..moves.len() {
-            for j in 0..i {
-                assert_ne!(moves[i], moves[j]);
+/// Bring a solution into a canonical order, such that solutions that only
+/// differ in the order of independent moves become identical.
+///
+/// Two moves are independent if they don't touch any common holes, in which
+/// case swapping them doesn't change the final position, and both orders are
+/// legal. We repeatedly pick the smallest move that could be played next
+/// without passing any move it depends on, which results in the
+/// lexicographically smallest equivalent solution.
+pub fn canonicalize_solution(moves: &[Move]) -> Vec<Move> {
+    let support = |mv: Move| mv.remove_bits | mv.add_bits;
+    let key = |mv: Move| (mv.src.hole_idx(), mv.dst.hole_idx());
+
+    let mut remaining = moves.to_vec();
+    let mut canonical = Vec::with_capacity(moves.len());
+    while !remaining.is_empty() {
+        let mut blocked = 0;
+        let mut best: Option<usize> = None;
+        for (i, &mv) in remaining.iter().enumerate() {
+            if support(mv) & blocked == 0 && best.is_none_or(|b| key(mv) < key(remaining[b])) {
+                best = Some(i);
             }
+            blocked |= support(mv);
         }
+        canonical.push(remaining.remove(best.expect("the first move is never blocked")));
     }
+    canonical
+}
 
-    #[test]
-    fn test_from_ascii() {
+/// Characters of the URL safe base64 alphabet, used by [`encode_solution`].
+const BASE64_URL_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Number of bits needed to store a move index, see [`Move::index`].
+const MOVE_INDEX_BITS: usize = 7;
+
+/// Encode a sequence of moves as a short string, suitable for URLs.
+///
+/// Every move is stored as its 7 bit index, and the resulting bit string is
+/// written in unpadded base64url. A full solution fits into 37 characters.
+pub fn encode_solution(moves: &[Move]) -> String {
+    let mut out = String::new();
+    let mut buffer = 0u32;
+    let mut nr_buffered_bits = 0;
+
+    for mv in moves {
+        buffer = buffer << MOVE_INDEX_BITS | mv.index() as u32;
+        nr_buffered_bits += MOVE_INDEX_BITS;
+        while nr_buffered_bits >= 6 {
+            nr_buffered_bits -= 6;
+            out.push(BASE64_URL_ALPHABET[(buffer >> nr_buffered_bits & 0x3f) as usize] as char);
+        }
+    }
+    if nr_buffered_bits > 0 {
+        let last = buffer << (6 - nr_buffered_bits) & 0x3f;
+        out.push(BASE64_URL_ALPHABET[last as usize] as char);
+    }
+
+    out
+}
+
+/// Inverse of [`encode_solution`].
+///
+/// Returns `None` if the string contains characters outside of the base64url
+/// alphabet, refers to a move index that doesn't exist, or isn't exactly in
+/// the form produced by [`encode_solution`]. This doesn't check whether the
+/// moves can actually be played one after the other.
+pub fn decode_solution(s: &str) -> Option<Vec<Move>> {
+    let nr_moves = s.len() * 6 / MOVE_INDEX_BITS;
+    if (nr_moves * MOVE_INDEX_BITS).div_ceil(6) != s.len() {
+        return None;
+    }
+
+    let mut moves = Vec::with_capacity(nr_moves);
+    let mut buffer = 0u32;
+    let mut nr_buffered_bits = 0;
+
+    for c in s.bytes() {
+        let value = BASE64_URL_ALPHABET.iter().position(|&a| a == c)?;
+        buffer = buffer << 6 | value as u32;
+        nr_buffered_bits += 6;
+        if nr_buffered_bits >= MOVE_INDEX_BITS && moves.len() < nr_moves {
+            nr_buffered_bits -= MOVE_INDEX_BITS;
+            let idx = buffer >> nr_buffered_bits & 0x7f;
+            moves.push(Move::from_index(idx as u8)?);
+        }
+    }
+
+    // The padding bits at the end have to be zero.
+    if buffer & ((1 << nr_buffered_bits) - 1) != 0 {
+        return None;
+    }
+
+    Some(moves)
+}
+
+/// Cached result of [`all_moves`].
+static ALL_MOVES: LazyLock<[Move; 76]> = LazyLock::new(all_moves);
+
+/// Index of every move in [`ALL_MOVES`], by source hole and direction, see
+/// [`Move::index`]. Entries without a move are left at `u8::MAX`.
+static MOVE_INDICES: LazyLock<[[u8; 4]; NR_HOLES]> = LazyLock::new(|| {
+    let mut indices = [[u8::MAX; 4]; NR_HOLES];
+    for (idx, mv) in ALL_MOVES.iter().enumerate() {
+        indices[mv.src.hole_idx() as usize][mv.direction() as usize] = idx as u8;
+    }
+    indices
+});
+
+/// The jump directions in the order of [`all_moves_by_direction`]. Every
+/// direction is the previous one rotated by [`Coord::rotate`].
+pub const MOVE_DIRECTIONS: [CardinalDir; 4] = [
+    CardinalDir::Right,
+    CardinalDir::Down,
+    CardinalDir::Left,
+    CardinalDir::Up,
+];
+
+/// All possible moves, grouped by the direction of the jump in the order of
+/// [`MOVE_DIRECTIONS`]. Concatenating the groups gives [`all_moves`].
+pub fn all_moves_by_direction() -> [Vec<Move>; 4] {
+    std::array::from_fn(|direction| {
+        Coord::all()
+            .into_iter()
+            .filter_map(|coord| {
+                let mut coord_a = coord;
+                let mut coord_b = coord_a.shift(1, 0)?;
+                let mut coord_c = coord_a.shift(2, 0)?;
+
+                for _ in 0..direction {
+                    coord_a = coord_a.rotate();
+                    coord_b = coord_b.rotate();
+                    coord_c = coord_c.rotate();
+                }
+
+                let remove_bits = coord_a.bitmask() | coord_b.bitmask();
+                let add_bits = coord_c.bitmask();
+                let mv = Move {
+                    remove_bits,
+                    add_bits,
+                    src: coord_a,
+                    dst: coord_c,
+                    middle: coord_b,
+                };
+                Some(mv)
+            })
+            .collect()
+    })
+}
+
+/// A list of all possible moves on a peg solitaire board.
+///
+/// This list does not take a current board position into account, therefore
+/// for a given board position only some of these moves will be applicable
+/// in this moment.
+pub fn all_moves() -> [Move; 76] {
+    let all: Vec<_> = all_moves_by_direction().into_iter().flatten().collect();
+
+    all.try_into().expect("should find exactly 76 moves")
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::proptest;
+    use rand::{RngCore, SeedableRng};
+    use tempfile::tempdir;
+
+    use crate::{coord::Coord, debruijn::de_bruijn_solvable};
+
+    use super::*;
+
+    #[test]
+    // test if the coordinate bits appear in the expected sequential order
+    fn test_coords() {
+        let mut next_mask = 1;
+        for coord in Coord::all() {
+            assert_eq!(next_mask, coord.bitmask());
+            next_mask *= 2;
+        }
+
+        assert_eq!(next_mask, 1u64 << 33);
+    }
+
+    #[test]
+    fn test_move_list_contains_all_unique_moves() {
+        let moves = all_moves();
+
+        for i in 0..moves.len() {
+            for j in 0..i {
+                assert_ne!(moves[i], moves[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_all_moves_by_direction() {
+        let groups = all_moves_by_direction();
+        assert_eq!(groups.concat(), all_moves());
+
+        for (moves, dir) in groups.iter().zip(MOVE_DIRECTIONS) {
+            assert_eq!(moves.len(), 19);
+            let (dx, dy) = dir.offset();
+            for mv in moves {
+                assert_eq!(mv.destination() - mv.source(), (2 * dx, 2 * dy));
+            }
+        }
+    }
+
+    #[test]
+    fn test_move_changed_coords() {
+        let mv = Move::from_raw_coords((2, 0), (0, 0));
+        let src = Coord::new(2, 0).unwrap();
+        let middle = Coord::new(1, 0).unwrap();
+        let dst = Coord::new(0, 0).unwrap();
+
+        assert_eq!(mv.changed_coords(), [src, middle, dst]);
+        assert_eq!(mv.added(), dst);
+        assert_eq!(mv.removed(), [src, middle]);
+
+        let bits = mv.changed_coords().map(Coord::bitmask);
+        assert_eq!(
+            bits.iter().fold(0, |a, b| a | b),
+            mv.remove_bits | mv.add_bits
+        );
+    }
+
+    #[test]
+    fn test_move_inverse() {
+        let start = Position::default_start();
+        for mv in all_moves() {
+            assert_eq!(mv.inverse().inverse(), mv);
+            assert_eq!(mv.inverse().source(), mv.destination());
+            assert_eq!(mv.inverse().destination(), mv.source());
+
+            let inverted = start.inverse();
+            assert_eq!(
+                inverted.can_move_inverse(mv),
+                mv.inverse().is_legal_in(inverted)
+            );
+            if start.can_move(mv) {
+                let pos = start.apply_move(mv);
+                assert!(mv.inverse().is_legal_in(pos));
+                assert_eq!(mv.inverse().apply_to(pos), pos.apply_move_inverse(mv));
+                assert_eq!(mv.inverse().apply_to(pos), start);
+            }
+        }
+    }
+
+    #[test]
+    fn test_move_from_src_dir() {
+        let center = Coord::center();
+        let expected = [
+            (CardinalDir::Up, (0, -2)),
+            (CardinalDir::Down, (0, 2)),
+            (CardinalDir::Left, (-2, 0)),
+            (CardinalDir::Right, (2, 0)),
+        ];
+        for (dir, dst) in expected {
+            assert_eq!(
+                Move::from_src_dir(center, dir),
+                Some(Move::from_raw_coords((0, 0), dst))
+            );
+        }
+
+        for mv in all_moves() {
+            assert_eq!(Move::from_src_dir(mv.source(), mv.direction()), Some(mv));
+        }
+        assert_eq!(
+            Move::from_raw_coords((-1, 0), (1, 0)).direction(),
+            CardinalDir::Right
+        );
+        assert_eq!(
+            Move::from_raw_coords((0, 2), (0, 0)).direction(),
+            CardinalDir::Up
+        );
+
+        let corner = Coord::new(-1, -3).unwrap();
+        assert_eq!(Move::from_src_dir(corner, CardinalDir::Up), None);
+        assert_eq!(Move::from_src_dir(corner, CardinalDir::Left), None);
+        assert_eq!(
+            Move::from_src_dir(corner, CardinalDir::Right),
+            Some(Move::from_raw_coords((-1, -3), (1, -3)))
+        );
+    }
+
+    #[test]
+    fn test_moves_from() {
+        let src = Coord::new(1, 0).unwrap();
+        let start = Position::default_start();
+        assert_eq!(start.moves_from(src).count(), 0);
+
+        let opened = start.apply_move(Move::from_raw_coords((-2, 0), (0, 0)));
+        let moves: Vec<_> = opened.moves_from(src).collect();
+        assert_eq!(moves, vec![Move::from_raw_coords((1, 0), (-1, 0))]);
+
+        // moves_from agrees with legal_moves for every source
+        for pos in [start, opened, Position::default_end()] {
+            for coord in Coord::all() {
+                let expected: Vec<_> = pos
+                    .legal_moves()
+                    .filter(|mv| mv.source() == coord)
+                    .collect();
+                let actual: Vec<_> = pos.moves_from(coord).collect();
+                assert_eq!(actual.len(), expected.len());
+                for mv in actual {
+                    assert!(expected.contains(&mv));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_full_board_except() {
+        assert_eq!(
+            Position::full_board_except(Coord::center()),
+            Position::default_start()
+        );
+
+        let corner = Coord::new(-1, -3).unwrap();
+        let pos = Position::full_board_except(corner);
+        assert_eq!(pos.count(), NR_PEGS as i32);
+        assert!(!pos.is_occupied(corner));
+        assert!(pos.is_occupied(Coord::center()));
+    }
+
+    #[test]
+    fn test_peg_distance() {
+        let start = Position::default_start();
+        let end = Position::default_end();
+        assert_eq!(start.peg_distance(&start), 0);
+        assert_eq!(start.peg_distance(&end), 31);
+        assert_eq!(end.peg_distance(&start), 31);
+
+        for mv in start.legal_moves() {
+            let next = start.apply_move(mv);
+            assert_eq!(start.peg_distance(&next), 1);
+        }
+
+        // Same number of pegs, but in different holes.
+        let a = Position(Coord::new(1, 0).unwrap().bitmask());
+        assert_eq!(a.peg_distance(&end), 1);
+        let b = Position(0b1111);
+        let c = Position(0b1111 << 20);
+        assert_eq!(b.peg_distance(&c), 3);
+    }
+
+    #[test]
+    fn test_hole_state() {
+        let pos = Position::default_start();
+        assert_eq!(pos.hole_state(0, 0), HoleState::Empty);
+        assert_eq!(pos.hole_state(1, 0), HoleState::Peg);
+        assert_eq!(pos.hole_state(-1, -3), HoleState::Peg);
+        assert_eq!(pos.hole_state(2, 2), HoleState::Invalid);
+        assert_eq!(pos.hole_state(4, 0), HoleState::Invalid);
+    }
+
+    #[test]
+    fn test_count_in() {
+        let center_block = Coord::all()
+            .into_iter()
+            .filter(|c| c.is_center_block())
+            .fold(0, |mask, c| mask | c.bitmask());
+        assert_eq!(Position::default_start().count_in(center_block), 8);
+        assert_eq!(Position::default_end().count_in(center_block), 1);
+
+        // Bits outside of the board don't count, even if the position has
+        // stray bits set.
+        assert_eq!(Position(!0).count_in(!0), NR_HOLES as u32);
+    }
+
+    #[test]
+    fn test_predecessors_of_end() {
+        let end = Position::default_end();
+        let predecessors = Position::predecessors_of_end();
+        assert_eq!(predecessors.len(), 4);
+        for pos in predecessors {
+            assert_eq!(pos.count(), 2);
+            assert!(pos.legal_moves().any(|mv| pos.apply_move(mv) == end));
+        }
+    }
+
+    #[test]
+    fn test_predecessors() {
+        for seed in 0..10 {
+            let pos = Position::random_solvable(seed as i32 + 5, seed);
+            let predecessors = pos.predecessors();
+            assert!(!predecessors.is_empty());
+            for prev in predecessors {
+                assert_eq!(prev.count(), pos.count() + 1);
+                assert!(prev.legal_moves().any(|mv| prev.apply_move(mv) == pos));
+            }
+        }
+        assert_eq!(Position::default_start().predecessors(), vec![]);
+    }
+
+    #[test]
+    fn test_normalized_forward_neighbors() {
+        // The four opening moves lead to symmetric positions.
+        let start = Position::default_start();
+        assert_eq!(start.legal_moves().count(), 4);
+        let opened = start.apply_move(Move::from_raw_coords((0, 2), (0, 0)));
+        assert_eq!(
+            start.normalized_forward_neighbors(),
+            vec![opened.normalize()]
+        );
+
+        // Jumping the centre peg to either side leads to mirrored positions.
+        let pos = Position::from_ascii([
+            "    ...    ",
+            "    ...    ",
+            "  .......  ",
+            "  ..###..  ",
+            "  .......  ",
+            "    ...    ",
+            "    ...    ",
+        ]);
+        let neighbors = pos.normalized_forward_neighbors();
+        assert_eq!(pos.legal_moves().count(), 2);
+        assert_eq!(neighbors.len(), 1);
+
+        assert_eq!(
+            Position::default_end().normalized_forward_neighbors(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_legal_moves_shuffled_yields_legal_moves() {
+        let mut rng = Pcg64Mcg::seed_from_u64(1);
+        for seed in 0..20 {
+            let pos = Position::random_solvable(seed as i32 + 2, seed);
+
+            let expected: Vec<_> = all_moves()
+                .into_iter()
+                .filter(|&mv| pos.can_move(mv))
+                .collect();
+            assert_eq!(pos.legal_moves().collect::<Vec<_>>(), expected);
+
+            let shuffled = pos.legal_moves_shuffled(&mut rng);
+            assert_eq!(shuffled.len(), expected.len());
+            for mv in shuffled {
+                assert!(expected.contains(&mv));
+            }
+        }
+    }
+
+    #[test]
+    fn test_solvable_with_filter() {
+        let filter = BloomFilter::accept_all();
+        let end = Position::default_end();
+        assert_eq!(
+            end.solvable_with_filter(&filter, Direction::Forward),
+            Solvability::Solved
+        );
+
+        let predecessor = Position::predecessors_of_end()[0];
+        assert_eq!(
+            predecessor.solvable_with_filter(&filter, Direction::Forward),
+            Solvability::Solvable
+        );
+
+        // An empty filter rules out every position.
+        let empty_filter = BloomFilter::new(1009, 1);
+        assert_eq!(
+            predecessor.solvable_with_filter(&empty_filter, Direction::Forward),
+            Solvability::Unsolvable
+        );
+
+        // A single peg next to the centre is in a different de Bruijn class
+        // than the end position, so even a filter that accepts everything
+        // can't make it solvable.
+        let wrong_class = Position::from_ascii([
+            "    ...    ",
+            "    ...    ",
+            "  .......  ",
+            "  ....#..  ",
+            "  .......  ",
+            "    ...    ",
+            "    ...    ",
+        ]);
+        assert_ne!(de_bruijn_class(wrong_class), de_bruijn_class(end));
+        assert_eq!(
+            wrong_class.solvable_with_filter(&filter, Direction::Forward),
+            Solvability::Unsolvable
+        );
+    }
+
+    #[test]
+    fn test_nearest_solvable() {
+        // The end position with a stray peg in a corner arm.
+        let pos = Position::from_ascii([
+            "    ...    ",
+            "    ...    ",
+            "  .......  ",
+            "  ...#..#  ",
+            "  .......  ",
+            "    ...    ",
+            "    ...    ",
+        ]);
+        let mut filter = BloomFilter::new(100_003, 1);
+        filter.insert(Position::default_end().normalize());
+        assert!(!filter.query(pos.normalize()));
+
+        assert_eq!(
+            nearest_solvable(pos, &filter),
+            Some(Position::default_end())
+        );
+        assert_eq!(nearest_solvable(pos, &BloomFilter::new(13, 1)), None);
+
+        // Removing a peg comes first, even if adding one would also pass.
+        let suggestion = nearest_solvable(pos, &BloomFilter::accept_all()).unwrap();
+        assert_eq!(suggestion.count(), pos.count() - 1);
+    }
+
+    #[test]
+    fn test_quick_solvability() {
+        let filter = BloomFilter::accept_all();
+        let start = Position::default_start();
+        let end = Position::default_end();
+
+        // Going backward, the start is a solved position.
+        assert_eq!(
+            quick_solvability(start, &filter, Direction::Backward),
+            Solvability::Solved
+        );
+        assert_eq!(
+            quick_solvability(end, &filter, Direction::Forward),
+            Solvability::Solved
+        );
+
+        // A filter that only contains the positions along one solution.
+        let mut path_filter = BloomFilter::new(100_003, 1);
+        let mut pos = start;
+        path_filter.insert(pos.normalize());
+        for mv in notation_solution() {
+            pos = pos.apply_move(mv);
+            path_filter.insert(pos.normalize());
+        }
+        assert_eq!(
+            quick_solvability(start, &path_filter, Direction::Forward),
+            Solvability::Solvable
+        );
+
+        let wrong_class = Position(Coord::new(1, 0).unwrap().bitmask());
+        assert_eq!(
+            quick_solvability(wrong_class, &filter, Direction::Forward),
+            Solvability::Unsolvable
+        );
+        assert_eq!(
+            quick_solvability(
+                Position::predecessors_of_end()[0],
+                &filter,
+                Direction::Forward
+            ),
+            Solvability::Solvable
+        );
+        assert!(!Solvability::Unsolvable.solvable());
+    }
+
+    #[test]
+    fn test_greedy_best_move() {
+        let start = Position::default_start();
+        let empty_filter = BloomFilter::new(1_000_003, 1);
+        assert_eq!(
+            start.greedy_best_move(&empty_filter, Direction::Forward),
+            None
+        );
+
+        // All four opening moves are symmetric, so the filter only needs a
+        // single position to contain all of them.
+        let opened = start.apply_move(Move::from_raw_coords((0, 2), (0, 0)));
+        let mut filter = BloomFilter::new(1_000_003, 1);
+        filter.insert(opened.normalize());
+        let mv = start.greedy_best_move(&filter, Direction::Forward).unwrap();
+        assert!(start.can_move(mv));
+        assert!(filter.query(start.apply_move(mv).normalize()));
+
+        // Prefer the child with more children in the filter.
+        let pos = Position::from_ascii([
+            "    ...    ",
+            "    ...    ",
+            "  .......  ",
+            "  .##.#..  ",
+            "  ....#..  ",
+            "    ...    ",
+            "    ...    ",
+        ]);
+        // Jumping b4-d4 completes a line of three pegs with more follow-up
+        // moves than any other first move.
+        let best = Move::from_notation("b4-d4").unwrap();
+        let filter = BloomFilter::accept_all();
+        assert_eq!(
+            pos.greedy_best_move(&filter, Direction::Forward),
+            Some(best)
+        );
+
+        let other = Move::from_notation("c4-a4").unwrap();
+        let mut filter = BloomFilter::new(1_000_003, 1);
+        filter.insert(pos.apply_move(other).normalize());
+        assert_eq!(
+            pos.greedy_best_move(&filter, Direction::Forward),
+            Some(other)
+        );
+
+        // Going backward from one move after the start leads back to it.
+        let mv = opened
+            .greedy_best_move(&empty_filter, Direction::Backward)
+            .unwrap();
+        assert_eq!(opened.apply_move_inverse(mv), start);
+    }
+
+    #[test]
+    fn test_move_index_roundtrip() {
+        for (idx, mv) in all_moves().into_iter().enumerate() {
+            assert_eq!(mv.index() as usize, idx);
+            assert_eq!(Move::from_index(mv.index()), Some(mv));
+        }
+        assert_eq!(Move::from_index(76), None);
+        assert_eq!(Move::from_index(u8::MAX), None);
+    }
+
+    #[test]
+    fn test_is_subset_of() {
+        let start = Position::default_start();
+        let end = Position::default_end();
+        let full = Position(ALL_HOLES_MASK);
+        assert!(start.is_subset_of(&full));
+        assert!(end.is_subset_of(&full));
+        assert!(!full.is_subset_of(&start));
+        assert!(start.is_subset_of(&start));
+        assert!(Position(0).is_subset_of(&end));
+
+        // The last peg ends up in the centre, which is the one empty hole at
+        // the start.
+        assert!(!end.is_subset_of(&start));
+        assert!(!start.is_subset_of(&end));
+    }
+
+    #[test]
+    fn test_random_play_terminates() {
+        let mut rng = Pcg64Mcg::seed_from_u64(0);
+        for _ in 0..20 {
+            let mut pos = Position::default_start();
+            while let Some((mv, next)) = pos.apply_random_move(&mut rng) {
+                assert!(pos.can_move(mv));
+                assert!(next.is_valid());
+                assert_eq!(next.count(), pos.count() - 1);
+                pos = next;
+            }
+            assert!(pos.is_terminal());
+            assert!(pos.count() >= 1);
+        }
+    }
+
+    #[test]
+    fn test_after_notation() {
+        let start = Position::default_start();
+        let mv = Move::from_raw_coords((0, 2), (0, 0));
+        assert_eq!(mv.to_notation(), "d6-d4");
+        assert_eq!(Move::from_notation("d6-d4"), Some(mv));
+
+        assert_eq!(start.after_notation("d6-d4"), Ok(start.apply_move(mv)));
+        assert_eq!(
+            start.after_notation("d4-d6"),
+            Err(NotationError::IllegalMove(Move::from_raw_coords(
+                (0, 0),
+                (0, 2)
+            )))
+        );
+        assert_eq!(
+            start.after_notation("d6-d5"),
+            Err(NotationError::InvalidNotation("d6-d5".to_string()))
+        );
+        assert!(start.after_notation("hello").is_err());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "illegal move")]
+    fn test_apply_illegal_move_panics_in_debug() {
+        // the source hole is empty in the start position
+        let mv = Move::from_raw_coords((0, 0), (2, 0));
+        Position::default_start().apply_move(mv);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "illegal inverse move")]
+    fn test_apply_illegal_inverse_move_panics_in_debug() {
+        let mv = Move::from_raw_coords((2, 0), (0, 0));
+        Position::default_start().apply_move_inverse(mv);
+    }
+
+    #[test]
+    fn test_from_ascii() {
         let a = Position::from_ascii([
             "    #..    ",
             "    ...    ",
@@ -684,11 +2839,127 @@ mod tests {
         fn test_from_ascii_reverses_print(mask in 0u64..8589934592) {
             let position = Position(mask);
             let ascii = format!("{position}");
-            let parsed = position_from_ascii_multiline(&ascii);
+            let parsed = Position::from_multiline(&ascii).unwrap();
             assert_eq!(position, parsed);
         }
     }
 
+    proptest! {
+        #[test]
+        fn test_num_legal_moves_matches_naive_count(mask in 0u64..8589934592) {
+            let position = Position(mask);
+            assert_eq!(position.num_legal_moves() as usize, position.legal_moves().count());
+            assert_eq!(position.is_terminal(), position.legal_moves().next().is_none());
+        }
+    }
+
+    #[test]
+    fn test_num_legal_moves() {
+        assert_eq!(Position::default_start().num_legal_moves(), 4);
+        assert_eq!(Position::default_start().inverse().num_legal_moves(), 0);
+        assert!(Position::default_end().is_terminal());
+        assert_eq!(Position(ALL_HOLES_MASK).num_legal_moves(), 0);
+    }
+
+    #[test]
+    fn test_from_multiline_with_flexible_whitespace() {
+        let expected = Position::default_start();
+        let text = "\n\n      ###   \n    # # #\n  #######  \t\n  ###.###\n  \n#######\n    ###\n    ###  \n\n";
+        assert_eq!(Position::from_multiline(text), Ok(expected));
+
+        let truncated = "
+            # # #
+            # # #
+        # # # # # # #
+        # # # . # # #
+        ";
+        assert_eq!(
+            Position::from_multiline(truncated),
+            Err(ParsePositionError::WrongNumberOfLines(4))
+        );
+        assert_eq!(
+            Position::from_multiline(&format!("{expected}").replace('#', "o")),
+            Err(ParsePositionError::InvalidChar('o'))
+        );
+        assert_eq!(
+            Position::from_multiline(&format!("{expected}").replacen("###", "####", 1)),
+            Err(ParsePositionError::WrongLineLength {
+                line: 0,
+                expected: 3,
+                found: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_tikz() {
+        let tikz = Position::default_start().to_tikz();
+
+        let count = |prefix| {
+            tikz.lines()
+                .filter(|line| line.trim_start().starts_with(prefix))
+                .count()
+        };
+        assert_eq!(count("\\fill") + count("\\draw"), NR_HOLES);
+        assert_eq!(count("\\fill"), NR_PEGS);
+        assert!(tikz.starts_with("\\begin{tikzpicture}"));
+        assert!(tikz.ends_with("\\end{tikzpicture}\n"));
+    }
+
+    #[test]
+    fn test_to_compact_unicode() {
+        let start = Position::default_start();
+        let compact = start.to_compact_unicode();
+        assert_eq!(
+            compact,
+            concat!("  ███  \n", "███▀███\n", "▀▀███▀▀\n", "  ▀▀▀  \n",)
+        );
+        let nr_display_lines = format!("{start}").lines().count();
+        assert_eq!(compact.lines().count(), nr_display_lines.div_ceil(2));
+
+        assert_eq!(Position(0).to_compact_unicode().trim(), "");
+    }
+
+    proptest! {
+        #[test]
+        fn test_compact_string_roundtrip(mask in 0u64..8589934592) {
+            let position = Position(mask);
+            let parsed = Position::from_compact_string(&position.to_compact_string());
+            assert_eq!(parsed, Some(position));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_legal_moves_by_direction_matches_naive(mask in 0u64..8589934592) {
+            let pos = Position(mask);
+            let mut fast: Vec<_> = pos.legal_moves_by_direction().map(Move::index).collect();
+            let mut naive: Vec<_> = pos.legal_moves().map(Move::index).collect();
+            fast.sort();
+            naive.sort();
+            assert_eq!(fast, naive);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_from_bits_drops_stray_bits(mask in 0u64..8589934592) {
+            let position = Position(mask);
+            assert_eq!(position.as_bits(), mask);
+            assert_eq!(Position::from_bits(position.as_bits() | (1 << 40)), position);
+            assert!(Position::from_bits(mask | (u64::MAX << 33)).is_valid());
+            assert_eq!(Position::new(mask), Some(position));
+            assert_eq!(Position::new(mask | (1 << 40)), None);
+        }
+    }
+
+    #[test]
+    fn test_compact_string_rejects_invalid_input() {
+        assert_eq!(Position::from_compact_string("hello"), None);
+        assert_eq!(Position::from_compact_string("200000000"), None);
+        assert_eq!(Position::from_compact_string(""), None);
+    }
+
     #[test]
     fn test_rotate() {
         let a = Position::from_ascii([
@@ -714,6 +2985,42 @@ mod tests {
         assert_eq!(a.rotate(), b);
     }
 
+    proptest! {
+        #[test]
+        fn test_rows_roundtrip(mask in 0u64..8589934592) {
+            let position = Position(mask);
+            let rows = position.rows();
+            assert_eq!(Position::from_rows(rows), Some(position));
+
+            // Mirroring flips the board upside down.
+            let mut reversed = rows;
+            reversed.reverse();
+            assert_eq!(position.mirror().rows(), reversed);
+        }
+    }
+
+    #[test]
+    fn test_rows() {
+        let top_row = Position::from_rows([0b111, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(
+            top_row,
+            Position::from_ascii([
+                "    ###    ",
+                "    ...    ",
+                "  .......  ",
+                "  .......  ",
+                "  .......  ",
+                "    ...    ",
+                "    ...    ",
+            ])
+        );
+        // Rotating turns the top row into the left column.
+        assert_eq!(top_row.rotate().rows(), [0, 0, 1, 1, 1, 0, 0]);
+
+        assert_eq!(Position::default_start().rows()[3], 0b1110111);
+        assert_eq!(Position::from_rows([0b1000, 0, 0, 0, 0, 0, 0]), None);
+    }
+
     #[test]
     fn test_mirror() {
         let a = Position::from_ascii([
@@ -739,21 +3046,73 @@ mod tests {
         assert_eq!(a.mirror(), b);
     }
 
-    #[test]
-    fn test_mirror_involutive() {
-        let mut rng = rand::rngs::StdRng::from_seed([5; 32]);
-        for _ in 0..500 {
-            let pos = rng.next_u64() & ((1u64 << 33) - 1);
-            let pos = Position(pos);
-
-            assert_eq!(pos.mirror().mirror(), pos);
+    /// Straightforward implementation of a board transformation, going through
+    /// the coordinates of every peg.
+    fn transform_reference(pos: Position, f: impl Fn(Coord) -> Coord) -> Position {
+        let mut out = 0;
+        for coord in Coord::all() {
+            if pos.is_occupied(coord) {
+                out |= f(coord).bitmask();
+            }
         }
+        Position(out)
     }
 
-    #[test]
-    fn test_normalize() {
-        let a = Position::from_ascii([
-            "    ...    ",
+    proptest! {
+        #[test]
+        fn test_rotate_matches_reference(mask in 0u64..8589934592, nr_rotations in 0..4) {
+            let mut position = Position(mask);
+            let mut expected = position;
+            for _ in 0..nr_rotations {
+                position = position.rotate();
+                // `Position::rotate` turns the board in the opposite direction
+                // to `Coord::rotate`.
+                expected = transform_reference(expected, |c| Coord::new(c.y(), -c.x()).unwrap());
+            }
+            assert_eq!(position, expected);
+        }
+
+        #[test]
+        fn test_mirror_matches_reference(mask in 0u64..8589934592) {
+            let position = Position(mask);
+            let expected = transform_reference(position, |c| Coord::new(c.x(), -c.y()).unwrap());
+            assert_eq!(position.mirror(), expected);
+        }
+    }
+
+    #[test]
+    fn test_mirror_involutive() {
+        let mut rng = rand::rngs::StdRng::from_seed([5; 32]);
+        for _ in 0..500 {
+            let pos = rng.next_u64() & ((1u64 << 33) - 1);
+            let pos = Position(pos);
+
+            assert_eq!(pos.mirror().mirror(), pos);
+        }
+    }
+
+    #[test]
+    fn test_inverse_ignores_stray_high_bits() {
+        let pos = Position(Position::default_start().0 | (1u64 << 40) | (1u64 << 63));
+        let inverse = pos.inverse();
+
+        assert_eq!(inverse, Position::default_end());
+        assert!(inverse.is_valid());
+    }
+
+    #[test]
+    fn test_is_valid() {
+        assert!(Position::default_start().is_valid());
+        assert!(Position(0).is_valid());
+        assert!(Position(ALL_HOLES_MASK).is_valid());
+        assert!(!Position(1u64 << 40).is_valid());
+        assert!(!Position(Position::default_end().0 | (1u64 << 33)).is_valid());
+    }
+
+    #[test]
+    fn test_normalize() {
+        let a = Position::from_ascii([
+            "    ...    ",
             "    ..#    ",
             "  .##....  ",
             "  ..#....  ",
@@ -771,7 +3130,7 @@ mod tests {
 
     #[test]
     fn test_solver_returns_valid_sequence_of_moves() {
-        let filter = BloomFilter::always_true();
+        let filter = BloomFilter::accept_all();
 
         let mut pos = Position::from_ascii([
             "    ...    ",
@@ -798,6 +3157,618 @@ mod tests {
         assert_eq!(pos, Position::default_end());
     }
 
+    #[test]
+    fn test_solve_info_filter_counters() {
+        let end = Position::default_end();
+        let pos = Position::from_ascii([
+            "    ...    ",
+            "    ...    ",
+            "  .......  ",
+            "  ..###..  ",
+            "  ...#...  ",
+            "    .#.    ",
+            "    ...    ",
+        ]);
+
+        // Every search step after the first one was entered through a filter
+        // hit.
+        let filter = BloomFilter::accept_all();
+        let (result, info) = solve_with_bloom_filter(pos, &filter, Direction::Forward, 0);
+        assert!(matches!(result, SolveResult::Solved(_)));
+        assert_eq!(info.nr_attempts, 1);
+        assert_eq!(info.nr_filter_misses, 0);
+        assert_eq!(info.nr_filter_hits, info.nr_steps - 1);
+
+        // With only the start position in the filter, every child of the
+        // start is queried once and pruned.
+        let mut filter = BloomFilter::new(1_000_003, 1);
+        filter.insert(pos.normalize());
+        let (result, info) = solve_with_bloom_filter(pos, &filter, Direction::Forward, 0);
+        assert!(result == SolveResult::Unsolvable);
+        let nr_queries = pos
+            .legal_moves()
+            .map(|mv| pos.apply_move(mv))
+            .filter(|&next| next != end && next.count() > end.count())
+            .count() as u32;
+        assert_eq!(info.nr_filter_hits + info.nr_filter_misses, nr_queries);
+        assert_eq!(info.nr_filter_hits, 0);
+    }
+
+    #[test]
+    fn test_solve_result_positions() {
+        let filter = BloomFilter::accept_all();
+        let start = Position::from_ascii([
+            "    ...    ",
+            "    ...    ",
+            "  .......  ",
+            "  ..###..  ",
+            "  ...#...  ",
+            "    .#.    ",
+            "    ...    ",
+        ]);
+
+        let result = solve_with_bloom_filter(start, &filter, Direction::Forward, 0).0;
+        let positions = result.positions(start).unwrap();
+        assert_eq!(positions.len(), 5);
+        assert_eq!(positions[0], start);
+        assert_eq!(*positions.last().unwrap(), Position::default_end());
+
+        assert_eq!(SolveResult::Unsolvable.positions(start), None);
+    }
+
+    #[test]
+    fn test_solution_steps() {
+        let filter = BloomFilter::accept_all();
+        let start = Position::from_ascii([
+            "    ...    ",
+            "    ...    ",
+            "  .......  ",
+            "  ..###..  ",
+            "  ...#...  ",
+            "    .#.    ",
+            "    ...    ",
+        ]);
+
+        let result = solve_with_bloom_filter(start, &filter, Direction::Forward, 0).0;
+        let solution = result.solution(start).unwrap();
+        assert_eq!(solution.final_position(), Position::default_end());
+
+        let steps: Vec<_> = solution.iter_steps().collect();
+        assert_eq!(steps.len(), solution.moves.len());
+        assert_eq!(steps[0].0, start);
+        for (before, mv, after) in &solution {
+            assert_eq!(before.apply_move(mv), after);
+        }
+        for pair in steps.windows(2) {
+            assert_eq!(pair[0].2, pair[1].0);
+        }
+
+        assert_eq!(SolveResult::Unsolvable.solution(start), None);
+    }
+
+    #[test]
+    fn test_bloom_solver_agrees_with_exact_solver() {
+        let end = Position::default_end();
+        let mut rng = Pcg64Mcg::seed_from_u64(0);
+        let holes: Vec<_> = Coord::all().into_iter().collect();
+
+        let mut positions: Vec<_> = (2..=8)
+            .map(|nr_pegs| Position::random_solvable(nr_pegs, nr_pegs as u64))
+            .collect();
+        // Random sparse positions are mostly unsolvable.
+        for _ in 0..50 {
+            let mut pos = Position(0);
+            for _ in 0..6 {
+                pos.0 |= holes[rng.random_range(..holes.len())].bitmask();
+            }
+            positions.push(pos);
+        }
+
+        // A small filter with lots of false positives, but without false
+        // negatives since every solvable position is inserted.
+        let mut filter = BloomFilter::new(4099, 1);
+        for &pos in &positions {
+            let mut pos = pos;
+            while let SolveResult::Solved(moves) = solve_exact(pos, end, Direction::Forward) {
+                filter.insert(pos.normalize());
+                let Some(&mv) = moves.first() else {
+                    break;
+                };
+                pos = pos.apply_move(mv);
+            }
+        }
+
+        let mut nr_solvable = 0;
+        for pos in positions {
+            let exact = solve_exact(pos, end, Direction::Forward);
+            let (approx, _) = solve_with_bloom_filter(pos, &filter, Direction::Forward, 0);
+            match approx {
+                SolveResult::Solved(moves) => {
+                    assert!(matches!(exact, SolveResult::Solved(_)), "{pos}");
+                    let positions = SolveResult::Solved(moves).positions(pos).unwrap();
+                    assert_eq!(positions.last(), Some(&end));
+                    nr_solvable += 1;
+                }
+                SolveResult::Unsolvable => assert!(exact == SolveResult::Unsolvable, "{pos}"),
+                SolveResult::TimedOut => {}
+            }
+        }
+        assert!(nr_solvable >= 7);
+    }
+
+    #[test]
+    fn test_estimate_random_success_rate() {
+        // Random play essentially never solves the full centre game, so we
+        // measure a solvable position closer to the end instead.
+        let end = Position::default_end();
+        let pos = Position::random_solvable(8, 0);
+        let estimate = |seed| {
+            let mut rng = Pcg64Mcg::seed_from_u64(seed);
+            estimate_random_success_rate(pos, end, 2000, &mut rng)
+        };
+        let rate = estimate(0);
+        assert!(rate > 0.0 && rate < 0.1, "unexpected rate {rate}");
+        assert_eq!(estimate(0), rate);
+        assert_ne!(estimate(1), rate);
+
+        let mut rng = Pcg64Mcg::seed_from_u64(0);
+        let rate = estimate_random_success_rate(Position::default_start(), end, 200, &mut rng);
+        assert!(rate < 0.01);
+
+        let mut rng = Pcg64Mcg::seed_from_u64(0);
+        assert_eq!(estimate_random_success_rate(end, end, 10, &mut rng), 1.0);
+    }
+
+    #[test]
+    fn test_symmetry_normalized_moves() {
+        let start = Position::default_start();
+        let moves = start.symmetry_normalized_moves();
+        assert_eq!(moves.len() * 4, start.legal_moves().count());
+
+        // Without symmetries, every move is kept.
+        let opened = start.apply_move(moves[0]);
+        let pos = opened.apply_move(opened.legal_moves().next().unwrap());
+        assert_eq!(pos.symmetry_count(), 1);
+        assert_eq!(
+            pos.symmetry_normalized_moves(),
+            pos.legal_moves().collect::<Vec<_>>()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_symmetry_normalized_moves_reach_all_neighbors(mask in 0u64..8589934592) {
+            let pos = Position(mask);
+            let mut neighbors: Vec<_> = pos
+                .symmetry_normalized_moves()
+                .into_iter()
+                .map(|mv| pos.apply_move(mv).normalize())
+                .collect();
+            neighbors.sort_by_key(|pos| pos.0);
+            neighbors.dedup();
+            assert_eq!(neighbors, pos.normalized_forward_neighbors());
+        }
+    }
+
+    #[test]
+    fn test_symmetry_count() {
+        assert_eq!(Position::default_start().symmetry_count(), 8);
+        assert_eq!(Position::default_end().symmetry_count(), 8);
+        let opened = Position::default_start().apply_move(Move::from_raw_coords((0, 2), (0, 0)));
+        assert_eq!(opened.symmetry_count(), 2);
+        let corner = Position(Coord::new(-1, -3).unwrap().bitmask());
+        assert_eq!(corner.symmetry_count(), 1);
+    }
+
+    #[test]
+    fn test_solve_prefers_symmetric_moves() {
+        let filter = BloomFilter::accept_all();
+        let end = Position::default_end();
+        let pos = Position::from_ascii([
+            "    ...    ",
+            "    .#.    ",
+            "  .......  ",
+            "  .#.##..  ",
+            "  .......  ",
+            "    .#.    ",
+            "    .#.    ",
+        ]);
+        let first_symmetry_count = |options| {
+            let (SolveResult::Solved(moves), _) =
+                solve_to_with_options(pos, end, &filter, Direction::Forward, 0, options)
+            else {
+                panic!("should be solvable");
+            };
+            pos.apply_move(moves[0]).symmetry_count()
+        };
+
+        let max_symmetry_count = pos
+            .legal_moves()
+            .map(|mv| pos.apply_move(mv))
+            .filter(|&child| solve_exact(child, end, Direction::Forward) != SolveResult::Unsolvable)
+            .map(|child| child.symmetry_count())
+            .max()
+            .unwrap();
+        assert_eq!(max_symmetry_count, 2);
+
+        let prefer_symmetric = SolveOptions {
+            prefer_symmetric: true,
+            ..SolveOptions::default()
+        };
+        assert_eq!(first_symmetry_count(prefer_symmetric), max_symmetry_count);
+        assert!(first_symmetry_count(SolveOptions::default()) < max_symmetry_count);
+    }
+
+    #[test]
+    fn test_max_total_steps() {
+        // Without any pruning, the solver gives up on a nearly full board.
+        let pos = Position::default_start().apply_move(Move::from_raw_coords((0, 2), (0, 0)));
+        let end = Position::default_end();
+        let filter = BloomFilter::accept_all();
+
+        let (result, info) = solve_to_with_bloom_filter(pos, end, &filter, Direction::Forward, 0);
+        assert!(result == SolveResult::TimedOut);
+        assert!(info.nr_steps > 1000);
+
+        for max_total_steps in [1, 49, 50, 51, 120, 1000] {
+            for seed in 0..5 {
+                let options = SolveOptions {
+                    max_total_steps: Some(max_total_steps),
+                    ..SolveOptions::default()
+                };
+                let (result, info) =
+                    solve_to_with_options(pos, end, &filter, Direction::Forward, seed, options);
+                assert!(result == SolveResult::TimedOut);
+                assert!(info.nr_steps <= max_total_steps);
+            }
+        }
+    }
+
+    #[test]
+    fn test_solve_exact_backward() {
+        let pos = Position::default_start().apply_move(Move::from_raw_coords((0, 2), (0, 0)));
+        let SolveResult::Solved(moves) =
+            solve_exact(pos, Position::default_end(), Direction::Backward)
+        else {
+            panic!("the inverse of a position one move from the start is solvable");
+        };
+        assert_eq!(moves, vec![Move::from_raw_coords((0, 2), (0, 0))]);
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        fn count_naive(pos: Position, end: Position) -> u64 {
+            if pos == end {
+                return 1;
+            }
+            pos.legal_moves()
+                .map(|mv| count_naive(pos.apply_move(mv), end))
+                .sum()
+        }
+
+        let filter = BloomFilter::accept_all();
+        let end = Position::default_end();
+
+        for nr_pegs in 2..=7 {
+            let pos = Position::random_solvable(nr_pegs, nr_pegs as u64);
+            assert_eq!(
+                count_solutions(pos, end, &filter, Direction::Forward, u64::MAX),
+                SolutionCount::Exact(count_naive(pos, end))
+            );
+        }
+
+        // A position with exactly four solutions.
+        let pos = Position::random_solvable(5, 5);
+        assert_eq!(count_naive(pos, end), 4);
+        assert_eq!(
+            count_solutions(pos, end, &filter, Direction::Forward, 4),
+            SolutionCount::AtLeast(4)
+        );
+        assert_eq!(
+            count_solutions(pos, end, &filter, Direction::Forward, 5),
+            SolutionCount::Exact(4)
+        );
+        assert_eq!(
+            count_solutions(pos.inverse(), end, &filter, Direction::Backward, 5),
+            SolutionCount::Exact(4)
+        );
+
+        // The default start has far too many solutions to count them all.
+        assert!(matches!(
+            count_solutions(
+                Position::default_start(),
+                end,
+                &filter,
+                Direction::Forward,
+                u64::MAX
+            ),
+            SolutionCount::AtLeast(_)
+        ));
+    }
+
+    #[test]
+    fn test_solve_to_single_peg_outside_of_centre() {
+        let filter = BloomFilter::accept_all();
+        let pos = Position::from_ascii([
+            "    ...    ",
+            "    ...    ",
+            "  .......  ",
+            "  .......  ",
+            "  ...#...  ",
+            "    .#.    ",
+            "    ...    ",
+        ]);
+        let end = Position::from_ascii([
+            "    ...    ",
+            "    ...    ",
+            "  .......  ",
+            "  .......  ",
+            "  .......  ",
+            "    ...    ",
+            "    .#.    ",
+        ]);
+
+        let SolveResult::Solved(moves) =
+            solve_to_with_bloom_filter(pos, end, &filter, Direction::Forward, 0).0
+        else {
+            panic!("should be solvable");
+        };
+        assert_eq!(moves, vec![Move::from_raw_coords((0, 1), (0, 3))]);
+    }
+
+    #[test]
+    fn test_solve_to_corner_end() {
+        let end = Position::from_ascii([
+            "    ...    ",
+            "    ...    ",
+            "  .......  ",
+            "  .......  ",
+            "  .......  ",
+            "    ...    ",
+            "    ..#    ",
+        ]);
+        assert_eq!(end.symmetry_count(), 1);
+        let pos = Position::from_ascii([
+            "    ...    ",
+            "    ...    ",
+            "  .......  ",
+            "  .......  ",
+            "  ....#..  ",
+            "    ..#    ",
+            "    ...    ",
+        ]);
+
+        // A filter that only contains the positions on the way to the end,
+        // without normalizing them.
+        let mut filter = BloomFilter::new(1009, 1);
+        filter.insert(pos);
+
+        let SolveResult::Solved(moves) =
+            solve_to_with_bloom_filter(pos, end, &filter, Direction::Forward, 0).0
+        else {
+            panic!("should be solvable");
+        };
+        assert_eq!(moves, vec![Move::from_raw_coords((1, 1), (1, 3))]);
+
+        // The mirrored position reaches a different corner, so it must not
+        // be accepted just because its normalization is in the filter.
+        let mirrored = pos.symmetric_images()[1];
+        assert!(mirrored != pos);
+        assert!(matches!(
+            solve_to_with_bloom_filter(mirrored, end, &filter, Direction::Forward, 0).0,
+            SolveResult::Unsolvable
+        ));
+    }
+
+    #[test]
+    fn test_distance_lower_bound_does_not_exceed_solution_length() {
+        let filter = BloomFilter::accept_all();
+        let end = Position::default_end();
+        let mut pos = Position::from_ascii([
+            "    ...    ",
+            "    ...    ",
+            "  .......  ",
+            "  ..###..  ",
+            "  ...#...  ",
+            "    .#.    ",
+            "    ...    ",
+        ]);
+
+        let SolveResult::Solved(moves) =
+            solve_with_bloom_filter(pos, &filter, Direction::Forward, 0).0
+        else {
+            panic!("should be solvable");
+        };
+
+        for (i, mv) in moves.iter().enumerate() {
+            let remaining = (moves.len() - i) as i32;
+            assert!(pos.distance_to_end_lower_bound(end) <= remaining);
+            pos = pos.apply_move(*mv);
+        }
+        assert_eq!(pos.distance_to_end_lower_bound(end), 0);
+    }
+
+    #[test]
+    fn test_reverse_solution_returns_to_start() {
+        let filter = BloomFilter::accept_all();
+        let start = Position::from_ascii([
+            "    ...    ",
+            "    ...    ",
+            "  .......  ",
+            "  ..###..  ",
+            "  ...#...  ",
+            "    .#.    ",
+            "    ...    ",
+        ]);
+
+        let SolveResult::Solved(moves) =
+            solve_with_bloom_filter(start, &filter, Direction::Forward, 0).0
+        else {
+            panic!("should be solvable");
+        };
+
+        let mut pos = start;
+        for &mv in &moves {
+            pos = pos.apply_move(mv);
+        }
+        assert_eq!(pos, Position::default_end());
+
+        for mv in reverse_solution(&moves) {
+            assert!(pos.can_move_inverse(mv));
+            pos = pos.apply_move_inverse(mv);
+        }
+        assert_eq!(pos, start);
+    }
+
+    /// A complete solution from the default start to the default end.
+    fn notation_solution() -> Vec<Move> {
+        "d2-d4 b3-d3 c1-c3 c4-c2 e1-c1 c1-c3 c6-c4 a5-c5 d5-b5 a3-a5 a5-c5 \
+            f5-d5 e7-e5 e4-e6 c7-e7 e7-e5 e2-e4 g3-e3 d3-f3 g5-g3 g3-e3 d5-f5 f5-f3 f3-d3 d3-b3 \
+            b3-b5 b5-d5 d4-b4 d6-d4 e4-c4 b4-d4"
+            .split_whitespace()
+            .map(|s| Move::from_notation(s).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_solution_codec_roundtrip() {
+        let solution = notation_solution();
+        let end = solution
+            .iter()
+            .fold(Position::default_start(), |pos, &mv| pos.apply_move(mv));
+        assert_eq!(end, Position::default_end());
+
+        let encoded = encode_solution(&solution);
+        assert_eq!(encoded.len(), 37);
+        assert!(
+            encoded
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_')
+        );
+        assert_eq!(decode_solution(&encoded), Some(solution.clone()));
+
+        for len in 0..solution.len() {
+            let prefix = &solution[..len];
+            assert_eq!(
+                decode_solution(&encode_solution(prefix)).as_deref(),
+                Some(prefix)
+            );
+        }
+    }
+
+    #[test]
+    fn test_solution_codec_rejects_invalid_input() {
+        // 127 is not a valid move index
+        assert_eq!(decode_solution("_w"), None);
+        assert_eq!(
+            decode_solution("AA"),
+            Some(vec![Move::from_index(0).unwrap()])
+        );
+        // nonzero padding bits
+        assert_eq!(decode_solution("AB"), None);
+        // not a length that the encoder produces
+        assert_eq!(decode_solution("A"), None);
+        assert_eq!(decode_solution("AAAAAAAA"), None);
+        // characters outside of the alphabet
+        assert_eq!(decode_solution("A="), None);
+        assert_eq!(decode_solution(""), Some(vec![]));
+    }
+
+    #[test]
+    fn test_canonicalize_solution() {
+        let start = Position::from_ascii([
+            "    .#.    ",
+            "    .#.    ",
+            "  .......  ",
+            "  ##...##  ",
+            "  ..#....  ",
+            "    .#.    ",
+            "    .#.    ",
+        ]);
+        let a = Move::from_raw_coords((0, -3), (0, -1));
+        let b = Move::from_raw_coords((0, 3), (0, 1));
+        let c = Move::from_raw_coords((-3, 0), (-1, 0));
+        let d = Move::from_raw_coords((3, 0), (1, 0));
+
+        // These four moves all touch separate holes.
+        let orderings = [[a, b, c, d], [d, c, b, a], [c, a, d, b], [b, d, a, c]];
+        let canonical = canonicalize_solution(&orderings[0]);
+        for moves in orderings {
+            assert_eq!(canonicalize_solution(&moves), canonical);
+        }
+
+        // The canonical solution is still legal and leads to the same position.
+        let play = |moves: &[Move]| {
+            moves.iter().fold(start, |pos, &mv| {
+                assert!(pos.can_move(mv));
+                pos.apply_move(mv)
+            })
+        };
+        assert_eq!(play(&canonical), play(&orderings[1]));
+
+        // Moves that depend on each other keep their order.
+        let e = Move::from_raw_coords((-1, 0), (-1, 2));
+        let canonical = canonicalize_solution(&[d, c, e, b, a]);
+        assert_eq!(play(&canonical), play(&[d, c, e, b, a]));
+        let c_idx = canonical.iter().position(|&mv| mv == c).unwrap();
+        let e_idx = canonical.iter().position(|&mv| mv == e).unwrap();
+        assert!(c_idx < e_idx);
+    }
+
+    #[test]
+    fn test_trap_severity() {
+        let pos = Position::from_ascii([
+            "    ...    ",
+            "    .#.    ",
+            "  ...#...  ",
+            "  .##.##.  ",
+            "  ...#...  ",
+            "    ...    ",
+            "    ...    ",
+        ]);
+        let moves: Vec<_> = pos.legal_moves().collect();
+        let nr_moves = moves.len() as u32;
+        assert_eq!(nr_moves, 6);
+
+        // Only allow the first move.
+        let mut filter = BloomFilter::new(100_003, 1);
+        filter.insert(pos.apply_move(moves[0]).normalize());
+        let safe = moves
+            .iter()
+            .filter(|&&mv| filter.query(pos.apply_move(mv).normalize()))
+            .count() as u32;
+        assert!((1..nr_moves).contains(&safe));
+        assert_eq!(pos.trap_severity(&filter), nr_moves - safe);
+
+        assert_eq!(pos.trap_severity(&BloomFilter::accept_all()), 0);
+        assert_eq!(pos.trap_severity(&BloomFilter::new(13, 1)), nr_moves);
+    }
+
+    #[test]
+    fn test_query_children() {
+        let filter = BloomFilter::accept_all();
+        let pos = Position::default_start();
+
+        let children = filter.query_children(pos, Direction::Forward);
+        assert_eq!(children.len(), 4);
+        for (mv, positive) in children {
+            assert!(pos.can_move(mv));
+            assert!(positive);
+        }
+
+        let parents = filter.query_children(Position::default_end(), Direction::Backward);
+        assert_eq!(parents.len(), 4);
+        assert!(parents.iter().all(|(_, positive)| *positive));
+
+        let empty = BloomFilter::new(13, 1);
+        assert!(
+            empty
+                .query_children(pos, Direction::Forward)
+                .iter()
+                .all(|(_, positive)| !positive)
+        );
+    }
+
     #[test]
     fn test_save_and_load_preserves_bloom_filter() {
         let mut filter = BloomFilter::new(13, 1);
@@ -808,7 +3779,7 @@ mod tests {
         let filename = tempdir.path().join("asdf.bin");
 
         filter.save_to_file(&filename);
-        let filter2 = BloomFilter::load_from_file(filename);
+        let filter2 = BloomFilter::load_from_file(filename).unwrap();
 
         dbg!(&filter.bits.0);
         dbg!(&filter2.bits.0);
@@ -820,4 +3791,198 @@ mod tests {
             assert_eq!(filter.query(pos), filter2.query(pos));
         }
     }
+
+    #[test]
+    fn test_insert_all_warns_about_overfull_filter() {
+        let positions: Vec<_> = (0..1000).map(Position).collect();
+
+        let mut tiny = BloomFilter::new(13, 1);
+        let warning = tiny.insert_all(positions.iter().copied());
+        assert_eq!(tiny.fill_ratio(), 1.0);
+        assert_eq!(warning, Some(OverfullFilter { fill_ratio: 1.0 }));
+
+        let mut large = BloomFilter::new(100_003, 1);
+        assert_eq!(large.fill_ratio(), 0.0);
+        assert_eq!(large.insert_all(positions.iter().copied()), None);
+        assert_eq!(large.fill_ratio(), 1000.0 / 100_003.0);
+    }
+
+    #[test]
+    fn test_recommended_size() {
+        // -1000 / ln(0.99) = 99499.58..
+        assert_eq!(BloomFilter::recommended_size(1000, 0.01, 1), 99500);
+        // -2 * 1000 / ln(1 - 0.1) = 18982.27..
+        assert_eq!(BloomFilter::recommended_size(1000, 0.01, 2), 18983);
+        assert_eq!(BloomFilter::recommended_size(0, 0.01, 1), 0);
+
+        for k in 1..=4 {
+            let nr_bits = BloomFilter::recommended_size(1_000_000, 0.05, k) as f64;
+            let expected_fpr = (1.0 - (-(k as f64) * 1_000_000.0 / nr_bits).exp()).powi(k as i32);
+            assert!(expected_fpr <= 0.05);
+        }
+    }
+
+    #[test]
+    fn test_load_rejects_other_format_version() {
+        let filter = BloomFilter::new(13, 1);
+        let tempdir = tempdir().unwrap();
+        let filename = tempdir.path().join("filter.bin");
+        filter.save_to_file(&filename);
+
+        let mut data = std::fs::read(&filename).unwrap();
+        assert!(BloomFilter::load_from_slice(&data).is_ok());
+
+        // bump the version in the header to simulate a file from a different
+        // version of the format
+        let other_version = BLOOM_FILTER_FORMAT_VERSION + 1;
+        data[..2].copy_from_slice(&other_version.to_le_bytes());
+        let err = BloomFilter::load_from_slice(&data).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unsupported bloom filter format version")
+        );
+    }
+
+    #[test]
+    fn test_load_accepts_unversioned_files() {
+        let mut filter = BloomFilter::new(65537, 1);
+        filter.insert(Position::default_end());
+        let unversioned = bincode::encode_to_vec(&filter, bincode_config()).unwrap();
+        // The low bytes of the size look like a version header.
+        assert_eq!(unversioned[..2], BLOOM_FILTER_FORMAT_VERSION.to_le_bytes());
+        assert_eq!(BloomFilter::load_from_slice(&unversioned).unwrap(), filter);
+
+        let mut filter = BlockedBloomFilter::new(1000, 3);
+        filter.insert(Position::default_end());
+        let unversioned = bincode::encode_to_vec(&filter, bincode_config()).unwrap();
+        assert_eq!(
+            BlockedBloomFilter::load_from_slice(&unversioned).unwrap(),
+            filter
+        );
+
+        // Truncated files are still rejected.
+        assert!(BlockedBloomFilter::load_from_slice(&unversioned[..20]).is_err());
+    }
+
+    #[test]
+    fn test_blocked_bloom_filter_has_no_false_negatives() {
+        let mut rng = rand::rngs::StdRng::from_seed([7; 32]);
+        let positions: Vec<_> = (0..2000)
+            .map(|_| Position(rng.next_u64() & ((1u64 << 33) - 1)))
+            .collect();
+
+        for k in 1..=MAX_BLOCKED_K {
+            let mut filter = BlockedBloomFilter::new(4096, k);
+            for &pos in &positions {
+                filter.insert(pos);
+            }
+            for &pos in &positions {
+                assert!(filter.query(pos), "false negative for {pos:?} with k={k}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_save_and_load_preserves_blocked_bloom_filter() {
+        let mut filter = BlockedBloomFilter::new(1000, 3);
+        filter.insert(Position(3));
+        filter.insert(Position(5));
+
+        let tempdir = tempdir().unwrap();
+        let filename = tempdir.path().join("blocked.bin");
+
+        filter.save_to_file(&filename);
+        let filter2 = BlockedBloomFilter::load_from_file(filename).unwrap();
+
+        assert_eq!(filter, filter2);
+    }
+
+    #[test]
+    fn test_hybrid_filter_is_exact_for_low_peg_counts() {
+        // A single bit bloom filter returns true for every query once
+        // anything has been inserted.
+        let mut filter = HybridFilter::new(BloomFilter::new(1, 1), 3);
+
+        let end = Position::default_end();
+        let two_pegs = end.apply_move_inverse(Move::from_raw_coords((0, 2), (0, 0)));
+        filter.insert(end);
+        filter.insert(Position::default_start());
+
+        assert!(filter.query(end));
+        assert!(!filter.query(two_pegs));
+        for nr_pegs in 1..=3 {
+            for seed in 0..10 {
+                let pos = Position::random_solvable(nr_pegs, seed);
+                assert_eq!(filter.query(pos), pos == end);
+            }
+        }
+
+        // above the limit, the bloom filter takes over
+        assert!(filter.query(Position::default_start()));
+        assert!(filter.query(Position::random_solvable(20, 0)));
+    }
+
+    #[test]
+    fn test_hybrid_filter_save_and_load() {
+        let mut filter = HybridFilter::new(BloomFilter::new(1009, 1), 3);
+        let positions = (1..=6).map(|nr_pegs| Position::random_solvable(nr_pegs, 0));
+        for pos in positions.clone() {
+            filter.insert(pos);
+        }
+
+        let dir = tempdir().unwrap();
+        let filename = dir.path().join("hybrid.bin");
+        filter.save_to_file(&filename);
+        let loaded = HybridFilter::load_from_file(&filename).unwrap();
+
+        assert_eq!(loaded.max_exact_pegs, filter.max_exact_pegs);
+        assert_eq!(loaded.exact, filter.exact);
+        assert!(loaded.bloom_filter == filter.bloom_filter);
+        for pos in positions {
+            assert!(loaded.query(pos));
+        }
+
+        let mut data = std::fs::read(&filename).unwrap();
+        data[..2].copy_from_slice(&(HYBRID_FILTER_FORMAT_VERSION + 1).to_le_bytes());
+        assert!(HybridFilter::load_from_slice(&data).is_err());
+    }
+
+    #[test]
+    fn test_random_reachable_from() {
+        let start = Position::default_start();
+        for seed in 0..10 {
+            let mut rng = Pcg64Mcg::seed_from_u64(seed);
+            let mut replay_rng = rng.clone();
+            let pos = Position::random_reachable_from(start, &mut rng, 10);
+            assert_eq!(pos.count(), start.count() - 10);
+
+            let mut replayed = start;
+            for _ in 0..10 {
+                let (mv, next) = replayed.apply_random_move(&mut replay_rng).unwrap();
+                assert!(replayed.can_move(mv));
+                replayed = next;
+            }
+            assert_eq!(replayed, pos);
+        }
+
+        // Stops at a terminal position instead of running out of steps.
+        let mut rng = Pcg64Mcg::seed_from_u64(0);
+        let pos = Position::random_reachable_from(start, &mut rng, 1000);
+        assert!(pos.count() < start.count());
+        assert_eq!(pos.num_legal_moves(), 0);
+    }
+
+    #[test]
+    fn test_random_solvable_has_requested_peg_count() {
+        for nr_pegs in [1, 2, 13, 24, 30, 31, NR_PEGS as i32] {
+            let pos = Position::random_solvable(nr_pegs, nr_pegs as u64);
+            assert_eq!(pos.count(), nr_pegs);
+            assert!(de_bruijn_solvable(pos));
+        }
+
+        // Out of range peg counts are clamped.
+        assert_eq!(Position::random_solvable(0, 0).count(), 1);
+        assert_eq!(Position::random_solvable(-5, 0).count(), 1);
+        assert_eq!(Position::random_solvable(40, 0), Position::default_start());
+    }
 }
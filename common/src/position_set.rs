@@ -0,0 +1,130 @@
+use rustc_hash::FxHashSet;
+
+use crate::Position;
+
+/// A set of positions where symmetric positions are considered equal.
+///
+/// Every position is normalized on insertion and lookup, so callers never
+/// have to remember to call [`Position::normalize`] themselves.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PositionSet {
+    positions: FxHashSet<Position>,
+}
+
+impl PositionSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert the position. Returns `true` if neither the position nor any of
+    /// its symmetric variants were present before.
+    pub fn insert(&mut self, position: Position) -> bool {
+        self.positions.insert(position.normalize())
+    }
+
+    /// Check if the position or any of its symmetric variants is present.
+    pub fn contains(&self, position: Position) -> bool {
+        self.positions.contains(&position.normalize())
+    }
+
+    /// Remove the position. Returns `true` if it was present.
+    pub fn remove(&mut self, position: Position) -> bool {
+        self.positions.remove(&position.normalize())
+    }
+
+    /// Number of positions in the set, modulo symmetry.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Iterate over the normalized positions in the set, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = Position> + use<'_> {
+        self.positions.iter().copied()
+    }
+
+    pub fn union(&self, other: &PositionSet) -> PositionSet {
+        Self {
+            positions: self.positions.union(&other.positions).copied().collect(),
+        }
+    }
+
+    pub fn intersection(&self, other: &PositionSet) -> PositionSet {
+        Self {
+            positions: self
+                .positions
+                .intersection(&other.positions)
+                .copied()
+                .collect(),
+        }
+    }
+
+    pub fn difference(&self, other: &PositionSet) -> PositionSet {
+        Self {
+            positions: self
+                .positions
+                .difference(&other.positions)
+                .copied()
+                .collect(),
+        }
+    }
+}
+
+impl FromIterator<Position> for PositionSet {
+    fn from_iter<T: IntoIterator<Item = Position>>(iter: T) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<Position> for PositionSet {
+    fn extend<T: IntoIterator<Item = Position>>(&mut self, iter: T) {
+        for position in iter {
+            self.insert(position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Move;
+
+    use super::*;
+
+    #[test]
+    fn test_symmetric_positions_are_deduplicated() {
+        let pos = Position::default_start().apply_move(Move::from_raw_coords((0, 2), (0, 0)));
+
+        let mut set = PositionSet::new();
+        assert!(set.insert(pos));
+        assert!(!set.insert(pos.rotate()));
+        assert!(!set.insert(pos.mirror()));
+        assert_eq!(set.len(), 1);
+        assert!(set.contains(pos.rotate().rotate()));
+        assert!(!set.contains(Position::default_start()));
+
+        assert!(set.remove(pos.mirror()));
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let start = Position::default_start();
+        let opened = start.apply_move(Move::from_raw_coords((0, 2), (0, 0)));
+        let end = Position::default_end();
+
+        let a: PositionSet = [start, opened].into_iter().collect();
+        let b: PositionSet = [opened.rotate(), end].into_iter().collect();
+
+        assert_eq!(a.union(&b).len(), 3);
+        assert_eq!(
+            a.intersection(&b).iter().collect::<Vec<_>>(),
+            vec![opened.normalize()]
+        );
+        assert_eq!(a.difference(&b).iter().collect::<Vec<_>>(), vec![start]);
+    }
+}
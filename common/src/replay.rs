@@ -0,0 +1,216 @@
+use std::fmt::Display;
+
+use crate::{Direction, Move, NR_PEGS, Position, bincode_config, coord::Coord};
+
+/// Version of the replay format, stored at the start of every replay.
+const REPLAY_FORMAT_VERSION: u32 = 1;
+
+/// A complete game, consisting of a start position and everything that
+/// happened afterwards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    pub start: Position,
+    pub entries: Vec<ReplayEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayEntry {
+    /// A move performed in the given direction.
+    Move(Move, Direction),
+    /// The board was edited, resulting in the given position.
+    Edit(Position),
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Decode(bincode::error::DecodeError),
+    UnsupportedVersion(u32),
+    /// The replay contains a position with bits set outside of the board, or
+    /// an empty or full board that can't be played.
+    InvalidPosition(u64),
+    /// The entry at the given index is not a legal move or edit.
+    InvalidEntry(usize),
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Decode(err) => write!(f, "could not decode replay: {err}"),
+            ReplayError::UnsupportedVersion(version) => {
+                write!(f, "unsupported replay format version {version}")
+            }
+            ReplayError::InvalidPosition(bits) => write!(f, "invalid position {bits:#x}"),
+            ReplayError::InvalidEntry(idx) => write!(f, "illegal entry at index {idx}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// On-disk representation of a [`Replay`]. Moves are stored as the hole
+/// indices of their source and destination.
+#[derive(bincode::Encode, bincode::Decode)]
+struct ReplayFile {
+    version: u32,
+    start: u64,
+    entries: Vec<ReplayFileEntry>,
+}
+
+#[derive(bincode::Encode, bincode::Decode)]
+enum ReplayFileEntry {
+    Forward { src: u8, dst: u8 },
+    Backward { src: u8, dst: u8 },
+    Edit { position: u64 },
+}
+
+/// Serialize a replay to bytes.
+pub fn save_replay(replay: &Replay) -> Vec<u8> {
+    let entries = replay
+        .entries
+        .iter()
+        .map(|entry| match *entry {
+            ReplayEntry::Move(mv, dir) => {
+                let src = mv.source().hole_idx();
+                let dst = mv.destination().hole_idx();
+                match dir {
+                    Direction::Forward => ReplayFileEntry::Forward { src, dst },
+                    Direction::Backward => ReplayFileEntry::Backward { src, dst },
+                }
+            }
+            ReplayEntry::Edit(pos) => ReplayFileEntry::Edit { position: pos.0 },
+        })
+        .collect();
+
+    let file = ReplayFile {
+        version: REPLAY_FORMAT_VERSION,
+        start: replay.start.0,
+        entries,
+    };
+    bincode::encode_to_vec(file, bincode_config()).expect("encoding into a vec can't fail")
+}
+
+/// Deserialize a replay and check that every entry is legal in the position
+/// it is applied to.
+pub fn load_replay(data: &[u8]) -> Result<Replay, ReplayError> {
+    let (file, _) = bincode::decode_from_slice::<ReplayFile, _>(data, bincode_config())
+        .map_err(ReplayError::Decode)?;
+    if file.version != REPLAY_FORMAT_VERSION {
+        return Err(ReplayError::UnsupportedVersion(file.version));
+    }
+
+    let to_position = |bits: u64| {
+        let pos = Position(bits);
+        (pos.is_valid() && (1..=NR_PEGS as i32).contains(&pos.count()))
+            .then_some(pos)
+            .ok_or(ReplayError::InvalidPosition(bits))
+    };
+    let start = to_position(file.start)?;
+
+    let mut pos = start;
+    let mut entries = Vec::with_capacity(file.entries.len());
+    for (idx, entry) in file.entries.into_iter().enumerate() {
+        let to_move = |src: u8, dst: u8| {
+            Move::from_coords(Coord::from_hole_idx(src)?, Coord::from_hole_idx(dst)?)
+        };
+
+        let entry = match entry {
+            ReplayFileEntry::Forward { src, dst } => {
+                let mv = to_move(src, dst).ok_or(ReplayError::InvalidEntry(idx))?;
+                if !pos.can_move(mv) {
+                    return Err(ReplayError::InvalidEntry(idx));
+                }
+                pos = pos.apply_move(mv);
+                ReplayEntry::Move(mv, Direction::Forward)
+            }
+            ReplayFileEntry::Backward { src, dst } => {
+                let mv = to_move(src, dst).ok_or(ReplayError::InvalidEntry(idx))?;
+                if !pos.can_move_inverse(mv) {
+                    return Err(ReplayError::InvalidEntry(idx));
+                }
+                pos = pos.apply_move_inverse(mv);
+                ReplayEntry::Move(mv, Direction::Backward)
+            }
+            ReplayFileEntry::Edit { position } => {
+                pos = to_position(position)?;
+                ReplayEntry::Edit(pos)
+            }
+        };
+        entries.push(entry);
+    }
+
+    Ok(Replay { start, entries })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ALL_HOLES_MASK;
+
+    fn sample_replay() -> Replay {
+        let start = Position::default_start();
+        let first = Move::from_raw_coords((2, 0), (0, 0));
+        let second = Move::from_raw_coords((0, 2), (0, 0));
+        let edited = start.apply_move(first).inverse();
+
+        Replay {
+            start,
+            entries: vec![
+                ReplayEntry::Move(first, Direction::Forward),
+                ReplayEntry::Move(first, Direction::Backward),
+                ReplayEntry::Move(second, Direction::Forward),
+                ReplayEntry::Edit(edited),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_replay_roundtrip() {
+        let replay = sample_replay();
+        let loaded = load_replay(&save_replay(&replay)).unwrap();
+        assert_eq!(loaded, replay);
+    }
+
+    #[test]
+    fn test_tampered_replay_is_rejected() {
+        let mut replay = sample_replay();
+        // Without undoing the first move, the second move would jump into
+        // an occupied hole.
+        replay.entries.swap(1, 2);
+
+        assert!(matches!(
+            load_replay(&save_replay(&replay)),
+            Err(ReplayError::InvalidEntry(1))
+        ));
+    }
+
+    #[test]
+    fn test_unplayable_positions_are_rejected() {
+        let empty = Replay {
+            start: Position(0),
+            entries: vec![],
+        };
+        assert!(matches!(
+            load_replay(&save_replay(&empty)),
+            Err(ReplayError::InvalidPosition(0))
+        ));
+
+        let mut full = sample_replay();
+        full.entries
+            .push(ReplayEntry::Edit(Position::default_end().inverse()));
+        full.entries
+            .push(ReplayEntry::Edit(Position(ALL_HOLES_MASK)));
+        assert!(matches!(
+            load_replay(&save_replay(&full)),
+            Err(ReplayError::InvalidPosition(ALL_HOLES_MASK))
+        ));
+    }
+
+    #[test]
+    fn test_truncated_replay_is_rejected() {
+        let data = save_replay(&sample_replay());
+        assert!(matches!(
+            load_replay(&data[..data.len() - 1]),
+            Err(ReplayError::Decode(_))
+        ));
+    }
+}
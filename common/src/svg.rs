@@ -0,0 +1,105 @@
+use std::fmt::Write;
+
+use crate::{Move, Position, coord::Coord};
+
+/// Distance between the centres of two neighbouring holes.
+const HOLE_DISTANCE: i32 = 40;
+const BOARD_SIZE: i32 = 7 * HOLE_DISTANCE;
+/// How far the move number is moved to the side of its arrow, so that the
+/// numbers of jumps over the same peg in opposite directions don't overlap.
+const LABEL_OFFSET: i32 = 8;
+
+/// Centre of the given hole in SVG user units.
+fn hole_center(coord: Coord) -> (i32, i32) {
+    let to_px = |v: i8| (v as i32 + 3) * HOLE_DISTANCE + HOLE_DISTANCE / 2;
+    (to_px(coord.x()), to_px(coord.y()))
+}
+
+/// Render the start position together with every jump of the solution on a
+/// single board. Each jump is drawn as an arrow from the source to the
+/// destination hole, labelled with its number starting at 1.
+///
+/// The moves are drawn as given, they are not checked against the position.
+/// The output only depends on the arguments, so it can be compared against a
+/// stored snapshot.
+pub fn render_solution_annotated_svg(start: Position, moves: &[Move]) -> String {
+    let mut svg = String::new();
+    let s = &mut svg;
+
+    // Writing to a String can't fail.
+    writeln!(
+        s,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {BOARD_SIZE} {BOARD_SIZE}" width="{BOARD_SIZE}" height="{BOARD_SIZE}">"#
+    )
+    .unwrap();
+    s.push_str(concat!(
+        "<defs>",
+        r#"<marker id="arrowhead" viewBox="0 0 10 10" refX="9" refY="5" "#,
+        r#"markerWidth="6" markerHeight="6" orient="auto-start-reverse">"#,
+        r##"<path d="M 0 0 L 10 5 L 0 10 z" fill="#c03030"/>"##,
+        "</marker>",
+        "</defs>\n",
+    ));
+
+    for coord in Coord::all() {
+        let (cx, cy) = hole_center(coord);
+        let fill = if start.is_occupied(coord) {
+            "#404040"
+        } else {
+            "#d8d8d8"
+        };
+        writeln!(s, r#"<circle cx="{cx}" cy="{cy}" r="12" fill="{fill}"/>"#).unwrap();
+    }
+
+    for (i, mv) in moves.iter().enumerate() {
+        let (x1, y1) = hole_center(mv.src);
+        let (x2, y2) = hole_center(mv.dst);
+        writeln!(
+            s,
+            r##"<line class="arrow" x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke="#c03030" stroke-width="2" marker-end="url(#arrowhead)"/>"##
+        )
+        .unwrap();
+
+        let (mut tx, mut ty) = ((x1 + x2) / 2, (y1 + y2) / 2);
+        if y1 == y2 {
+            ty -= LABEL_OFFSET;
+        } else {
+            tx += LABEL_OFFSET;
+        }
+        writeln!(
+            s,
+            r#"<text x="{tx}" y="{ty}" font-size="11" font-family="sans-serif">{}</text>"#,
+            i + 1
+        )
+        .unwrap();
+    }
+
+    s.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_solution_annotated_svg() {
+        let start = Position::default_start();
+        let moves = [
+            Move::from_raw_coords((0, 2), (0, 0)),
+            Move::from_raw_coords((-2, 1), (0, 1)),
+            Move::from_raw_coords((-1, -1), (-1, 1)),
+        ];
+        let svg = render_solution_annotated_svg(start, &moves);
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches(r#"class="arrow""#).count(), moves.len());
+        assert_eq!(svg.matches("<circle").count(), 33);
+        assert!(svg.contains(">3</text>"));
+        assert_eq!(svg, render_solution_annotated_svg(start, &moves));
+
+        let empty = render_solution_annotated_svg(start, &[]);
+        assert_eq!(empty.matches(r#"class="arrow""#).count(), 0);
+    }
+}
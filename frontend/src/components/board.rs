@@ -11,6 +11,14 @@ pub struct BoardProps {
     pub show_ui_buttons: bool,
     pub edit_mode: bool,
     pub selected: Option<Coord>,
+    /// Identity of the selected peg, i.e. its index in `pegs`
+    pub selected_peg: Option<u8>,
+    /// Holes that the selected peg can jump to
+    pub move_targets: Vec<Coord>,
+    /// Pegs that have at least one legal move
+    pub movable_pegs: Vec<Coord>,
+    /// Pegs without any neighbouring pegs, which are shown dimmed
+    pub isolated_pegs: Vec<Coord>,
     pub reset: Callback<()>,
     pub undo: Option<Callback<()>>,
     pub redo: Option<Callback<()>>,
@@ -30,6 +38,10 @@ pub fn Board(
         show_ui_buttons,
         edit_mode,
         selected,
+        selected_peg,
+        move_targets,
+        movable_pegs,
+        isolated_pegs,
         reset,
         undo,
         redo,
@@ -77,7 +89,7 @@ pub fn Board(
     if *tutorial_glow {
         // Is one of the four pegs selected that can be moved to
         // the middle in the first move?
-        let movable_peg_selected = selected.map_or(false, is_firstjump_peg);
+        let movable_peg_selected = selected.is_some_and(is_firstjump_peg);
 
         if movable_peg_selected {
             // If so, highlight the centre to show the player
@@ -91,12 +103,16 @@ pub fn Board(
     let cell_classes = {
         move |coord: Coord| {
             let is_selected = *selected == Some(coord) && !edit_mode;
-            let is_tutorial_glowing = glow_central_piece && coord == Coord::center()
+            let is_move_target = move_targets.contains(&coord) && !edit_mode;
+            let is_movable = movable_pegs.contains(&coord) && !edit_mode;
+            let is_tutorial_glowing = glow_central_piece && coord.is_center()
                 || glow_outer_pieces && is_firstjump_peg(coord);
 
             classes!(
                 "game-cell",
                 is_selected.then_some("selected"),
+                is_move_target.then_some("move-target"),
+                is_movable.then_some("movable"),
                 is_tutorial_glowing.then_some("tutorial-glow")
             )
         }
@@ -148,9 +164,15 @@ pub fn Board(
             { for pegs.iter().enumerate().map(|(i, p)| {
                 let left = PX_HOLE_DISTANCE * (p.coord.x() as i16 + 3);
                 let top = PX_HOLE_DISTANCE * (p.coord.y() as i16 + 3);
+                let is_isolated = p.alive && isolated_pegs.contains(&p.coord);
+                let is_selected = *selected_peg == Some(i as u8) && !edit_mode;
                 html!{
                     <div
-                        class="peg"
+                        class={classes!(
+                            "peg",
+                            is_isolated.then_some("isolated"),
+                            is_selected.then_some("selected")
+                        )}
                         key={i}
                         style={format!("left: {left}px; top: {top}px; opacity: {};", b2f(p.alive))}
                     />
@@ -163,5 +185,6 @@ pub fn Board(
 /// Is this one of the four coordinates of the pegs that can
 /// be moved to the centre in the first move?
 fn is_firstjump_peg(coord: Coord) -> bool {
-    coord.x() == 0 && coord.y().abs() == 2 || coord.x().abs() == 2 && coord.y() == 0
+    // These are the arm holes on the centre lines, right next to the centre block.
+    coord.arm().is_some() && coord.manhattan_distance(Coord::center()) == 2
 }
@@ -1,4 +1,5 @@
 pub mod board;
+pub mod share;
 pub mod timeline;
 
 /// Convert a bool to a float, which is useful for CSS opacity
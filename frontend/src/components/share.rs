@@ -0,0 +1,164 @@
+use common::Position;
+use qrcode::{Color, QrCode};
+use yew::prelude::*;
+use yew_hooks::prelude::*;
+
+/// Query parameter that carries the compact position string in a share URL.
+pub const POSITION_QUERY_PARAM: &str = "position";
+
+/// Query parameter that carries a percent-encoded game, as exported by
+/// [`GameState::to_json`](crate::game_state::GameState::to_json).
+pub const GAME_QUERY_PARAM: &str = "game";
+
+/// Number of light modules around the QR code, as required by the standard.
+const QUIET_ZONE: usize = 4;
+
+/// Build the URL under which the given position can be opened.
+pub fn share_url(base_url: &str, pos: Position) -> String {
+    format!(
+        "{base_url}?{POSITION_QUERY_PARAM}={}",
+        pos.to_compact_string()
+    )
+}
+
+/// Extract the position from the query string of a share URL, e.g.
+/// `?position=1fffeffff`.
+///
+/// Positions that can't be played, i.e. empty or full boards, are rejected.
+pub fn position_from_query(query: &str) -> Option<Position> {
+    let value = query_param(query, POSITION_QUERY_PARAM)?;
+    let pos = Position::from_compact_string(value)?;
+    (1..=common::NR_PEGS as i32)
+        .contains(&pos.count())
+        .then_some(pos)
+}
+
+/// Extract the JSON of a game from the query string, e.g.
+/// `?game=%7B%22start%22...`.
+pub fn game_json_from_query(query: &str) -> Option<String> {
+    let value = query_param(query, GAME_QUERY_PARAM)?;
+    js_sys::decode_uri_component(value).ok()?.as_string()
+}
+
+/// Build a link target that downloads the given JSON as a file.
+pub fn json_data_url(json: &str) -> String {
+    format!(
+        "data:application/json,{}",
+        String::from(js_sys::encode_uri_component(json))
+    )
+}
+
+/// Raw value of the first query parameter with the given name.
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .trim_start_matches('?')
+        .split('&')
+        .find_map(|param| param.strip_prefix(name)?.strip_prefix('='))
+}
+
+/// The modules of a QR code, stored row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QrModules {
+    width: usize,
+    dark: Vec<bool>,
+}
+
+impl QrModules {
+    pub fn encode(data: &str) -> Option<Self> {
+        let code = QrCode::new(data.as_bytes()).ok()?;
+        Some(Self {
+            width: code.width(),
+            dark: code
+                .to_colors()
+                .into_iter()
+                .map(|c| c == Color::Dark)
+                .collect(),
+        })
+    }
+
+    /// Build an SVG path that draws one unit square per dark module.
+    fn svg_path(&self) -> String {
+        let mut path = String::new();
+        for (i, _) in self.dark.iter().enumerate().filter(|(_, dark)| **dark) {
+            let x = i % self.width + QUIET_ZONE;
+            let y = i / self.width + QUIET_ZONE;
+            path += &format!("M{x} {y}h1v1h-1z");
+        }
+        path
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ShareQrCodeProps {
+    pub position: Position,
+}
+
+/// A QR code linking to the current position, for sharing it with someone
+/// in person.
+#[function_component]
+pub fn ShareQrCode(ShareQrCodeProps { position }: &ShareQrCodeProps) -> Html {
+    // Avoid re-encoding the QR code on every step while scrolling through a
+    // solution.
+    let debounced_position = use_debounce_state(|| *position, 300);
+    {
+        let debounced_position = debounced_position.clone();
+        use_effect_with(*position, move |position| {
+            debounced_position.set(*position);
+        });
+    }
+
+    let Some(location) = web_sys::window().map(|w| w.location()) else {
+        return html! {};
+    };
+    let base_url = format!(
+        "{}{}",
+        location.origin().unwrap_or_default(),
+        location.pathname().unwrap_or_default()
+    );
+    let Some(modules) = QrModules::encode(&share_url(&base_url, *debounced_position)) else {
+        return html! {};
+    };
+    let size = modules.width + 2 * QUIET_ZONE;
+
+    html! {
+        <svg
+            viewBox={format!("0 0 {size} {size}")}
+            style="width: 3rem; height: 3rem; display: block; margin: 0.5em auto 0"
+            shape-rendering="crispEdges"
+        >
+            <rect width={size.to_string()} height={size.to_string()} fill="white" />
+            <path d={modules.svg_path()} fill="black" />
+        </svg>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_url_can_be_encoded() {
+        let pos = Position::default_start();
+        let url = share_url("https://example.com/pegsolitaire/", pos);
+        assert_eq!(url, "https://example.com/pegsolitaire/?position=1fffeffff");
+
+        let modules = QrModules::encode(&url).unwrap();
+        assert_eq!(modules.dark.len(), modules.width * modules.width);
+        assert!(modules.dark.iter().any(|d| *d));
+    }
+
+    #[test]
+    fn test_position_from_query() {
+        let pos = Position::default_start();
+        let url = share_url("", pos);
+        assert_eq!(position_from_query(&url), Some(pos));
+        assert_eq!(
+            position_from_query("?a=b&position=000010000"),
+            Some(Position::default_end())
+        );
+
+        assert_eq!(position_from_query(""), None);
+        assert_eq!(position_from_query("?position=000000000"), None);
+        assert_eq!(position_from_query("?position=xyz"), None);
+    }
+}
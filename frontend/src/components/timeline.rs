@@ -2,13 +2,16 @@ use common::Direction;
 use web_sys::HtmlElement;
 use yew::prelude::*;
 
-use crate::{components::b2f, game_state::Solvability};
+use crate::{
+    components::b2f,
+    game_state::{Solvability, SolveTimeline},
+};
 
 #[derive(Properties, PartialEq, Clone)]
 pub struct TimelineProps {
-    pub nr_pegs: i32,
-    pub solvability_backward: Solvability,
-    pub solvability_forward: Solvability,
+    pub timeline: SolveTimeline,
+    /// The solver is still being downloaded.
+    pub loading: bool,
     pub scroll_to: Callback<i32>,
     pub step: Callback<Direction>,
 }
@@ -19,13 +22,16 @@ pub struct TimelineProps {
 #[function_component]
 pub fn Timeline(
     TimelineProps {
-        nr_pegs,
-        solvability_backward,
-        solvability_forward,
+        timeline,
+        loading,
         scroll_to,
         step,
     }: &TimelineProps,
 ) -> Html {
+    let nr_pegs = timeline.nr_pegs();
+    let solvability_backward = timeline.backward;
+    let solvability_forward = timeline.forward;
+
     let scroll_to_start = {
         let scroll_to = scroll_to.clone();
         move |_| scroll_to.emit(32)
@@ -45,20 +51,24 @@ pub fn Timeline(
 
     html! {
         <div style="display: flex; flex-direction: row; width: 100%; text-align: center; align-items: stretch; user-select: none; margin-bottom: 1em">
-            <TimelineEndpoint solvability={*solvability_backward} side={Side::Left} callback={scroll_to_start} />
+            <TimelineEndpoint solvability={solvability_backward} side={Side::Left} callback={scroll_to_start} />
             <div style="flex-grow: 1; display: flex; flex-direction: row">
-                <TimelineSegment solvability={*solvability_backward} len={32 - nr_pegs} side={Side::Left} callback={step_backward}/>
+                <TimelineSegment solvability={solvability_backward} len={32 - nr_pegs} side={Side::Left} callback={step_backward}/>
                 <div class="timeline-segment" style="align-items: center">
                     <span class="timeline-segment-upper" style="font-size: 0.35rem">
-                        {nr_pegs}
+                        if *loading {
+                            {"loading solver..."}
+                        } else {
+                            {nr_pegs}
+                        }
                     </span>
                     <div class="timeline-segment-lower">
                         <img src="img/circle.svg"/>
                     </div>
                 </div>
-                <TimelineSegment solvability={*solvability_forward} len={nr_pegs - 1} side={Side::Right} callback={step_forward}/>
+                <TimelineSegment solvability={solvability_forward} len={nr_pegs - 1} side={Side::Right} callback={step_forward}/>
             </div>
-            <TimelineEndpoint solvability={*solvability_forward} side={Side::Right} callback={scroll_to_end} />
+            <TimelineEndpoint solvability={solvability_forward} side={Side::Right} callback={scroll_to_end} />
         </div>
     }
 }
@@ -88,11 +98,7 @@ fn TimelineSegment(
         callback,
     }: &TimelineSegmentProps,
 ) -> Html {
-    let (color, borderstyle, clickable) = match solvability {
-        Solvability::Solvable | Solvability::Solved => ("#555", "solid", true),
-        Solvability::Unsolvable => ("#822", "dotted", false),
-        Solvability::Unknown => ("#882", "dashed", false),
-    };
+    let (color, borderstyle, clickable) = segment_line_style(*solvability);
     let outer_margin = 4;
     let inner_margin = if *len > 0 { outer_margin } else { 0 };
     let margins = match side {
@@ -148,6 +154,17 @@ fn TimelineSegment(
     }
 }
 
+/// Colour and border style of the line in a timeline segment, and whether the
+/// segment can be clicked to step along the solution.
+fn segment_line_style(solvability: Solvability) -> (&'static str, &'static str, bool) {
+    match solvability {
+        Solvability::Solvable | Solvability::Solved => ("#555", "solid", true),
+        Solvability::Unsolvable => ("#822", "dotted", false),
+        Solvability::Unknown => ("#882", "dashed", false),
+        Solvability::TimedOut => ("#a62", "dashed", false),
+    }
+}
+
 #[derive(Properties, Clone, PartialEq)]
 struct TimelineEndpointProps {
     solvability: Solvability,
@@ -192,3 +209,22 @@ fn TimelineEndpoint(
         </div>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use common::SolveResult;
+
+    use super::*;
+
+    #[test]
+    fn test_timed_out_solve_is_shown_distinctly() {
+        let solvability = Solvability::from(&SolveResult::TimedOut);
+        assert_eq!(solvability, Solvability::TimedOut);
+        assert_eq!(segment_line_style(solvability), ("#a62", "dashed", false));
+
+        let unknown = segment_line_style(Solvability::Unknown);
+        let unsolvable = segment_line_style(Solvability::from(&SolveResult::Unsolvable));
+        assert_ne!(unknown, segment_line_style(solvability));
+        assert_ne!(unsolvable, segment_line_style(solvability));
+    }
+}
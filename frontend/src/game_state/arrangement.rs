@@ -9,30 +9,40 @@ pub struct Arrangement {
     /// Current permutation of pegs on the board. The `forward` method maps
     /// from hole indices to peg identities.
     permutation: Permutation<NR_HOLES>,
-    // todo: reduce size
-    alive: [bool; NR_HOLES],
+    /// Location of every peg, indexed by peg identity. This is kept in sync
+    /// with the permutation, so that rendering doesn't have to rebuild it.
+    pegs: [Peg; NR_HOLES],
+    /// The occupied holes, kept in sync with `pegs`.
+    position: Position,
 }
 impl Arrangement {
     pub fn new() -> Self {
-        let mut alive = [true; NR_HOLES];
-        alive[16] = false;
+        let pegs = Coord::ALL.map(|coord| Peg {
+            coord,
+            alive: !coord.is_center(),
+        });
 
         Self {
             permutation: Permutation::new(),
-            alive,
+            pegs,
+            position: Position::default_start(),
         }
     }
 
-    pub fn pegs(&self) -> [Peg; NR_HOLES] {
-        let coords: Vec<_> = Coord::all().into_iter().collect();
-
-        std::array::from_fn(|id| {
-            let hole_idx = self.permutation.backward(id as u8) as usize;
-            Peg {
-                coord: coords[hole_idx],
-                alive: self.alive[id],
+    /// Construct an arrangement where every peg is still in its initial hole,
+    /// with the pegs alive according to the given position.
+    pub fn from_position(pos: Position) -> Self {
+        let mut arrangement = Self::new();
+        for coord in Coord::all() {
+            if arrangement.is_occupied(coord) != pos.is_occupied(coord) {
+                arrangement.toggle_hole(coord);
             }
-        })
+        }
+        arrangement
+    }
+
+    pub fn pegs(&self) -> [Peg; NR_HOLES] {
+        self.pegs
     }
 
     /// Perform a move from the given source to the destination coordinate.
@@ -47,23 +57,23 @@ impl Arrangement {
         let dst_hole_idx = dst.hole_idx();
         let src_peg_id = self.permutation.forward(src_hole_idx) as usize;
         let dst_peg_id = self.permutation.forward(dst_hole_idx) as usize;
-        let middle_peg_id = self.permutation.forward(middle.hole_idx()) as usize;
+        let is_occupied = |coord| self.is_occupied(coord);
         match dir {
-            Direction::Forward => if !self.alive[src_peg_id] {
+            Direction::Forward => if !is_occupied(src) {
                 Err(anyhow!("No peg in source position {src}"))
-            } else if self.alive[dst_peg_id] {
+            } else if is_occupied(dst) {
                 Err(anyhow!("Destination position {dst} is occupied"))
-            } else if !self.alive[middle_peg_id] {
+            } else if !is_occupied(middle) {
                 Err(anyhow!("No peg in middle position {middle}"))
             } else {
                 Ok(())
             }
             .with_context(|| format!("Cannot perform forward move with src={src}, dst={dst}"))?,
-            Direction::Backward => if self.alive[src_peg_id] {
+            Direction::Backward => if is_occupied(src) {
                 Err(anyhow!("No hole in source position {src}"))
-            } else if !self.alive[dst_peg_id] {
+            } else if !is_occupied(dst) {
                 Err(anyhow!("No peg in destination position {dst}"))
-            } else if self.alive[middle_peg_id] {
+            } else if is_occupied(middle) {
                 Err(anyhow!("No hole in middle position {middle}"))
             } else {
                 Ok(())
@@ -75,6 +85,11 @@ impl Arrangement {
         // has an invisible peg in every empty hole, we move that invisible peg from the destination position
         // to the soruce position. I.e., we just swap the two positions effectively.
         self.permutation.swap(src_hole_idx, dst_hole_idx);
+        self.pegs[src_peg_id].coord = dst;
+        self.pegs[dst_peg_id].coord = src;
+        // One of the two holes was occupied and the other one was empty, so
+        // swapping them toggles both.
+        self.position = Position(self.position.0 ^ src.bitmask() ^ dst.bitmask());
 
         // Toggle the peg in the middle positon
         self.toggle_hole(middle);
@@ -83,27 +98,65 @@ impl Arrangement {
     }
 
     pub fn nr_pegs(&self) -> usize {
-        self.alive.iter().fold(0, |i, b| i + *b as usize)
+        self.position.count() as usize
     }
 
     pub fn toggle_hole(&mut self, coord: Coord) {
         let peg_id = self.permutation.forward(coord.hole_idx());
-        self.alive[peg_id as usize] ^= true;
+        self.pegs[peg_id as usize].alive ^= true;
+        self.position = Position(self.position.0 ^ coord.bitmask());
     }
 
     pub fn as_position(&self) -> Position {
-        let mut out = 0;
-        for p in self.pegs() {
-            if p.alive {
-                out |= p.coord.bitmask();
-            }
-        }
-        Position(out)
+        self.position
     }
 
     pub fn is_occupied(&self, coord: Coord) -> bool {
+        self.position.is_occupied(coord)
+    }
+
+    /// Identity of the peg at the given coordinate, or `None` if the hole is
+    /// empty. Peg ids are stable across moves and match the indices of the
+    /// array returned by [`Self::pegs`].
+    pub fn peg_id_at(&self, coord: Coord) -> Option<u8> {
         let peg_id = self.permutation.forward(coord.hole_idx());
-        self.alive[peg_id as usize]
+        self.is_occupied(coord).then_some(peg_id)
+    }
+
+    /// Rebuild the pegs from the permutation alone, to check the cached
+    /// `pegs` and `position`.
+    #[cfg(test)]
+    fn recompute_pegs(&self) -> [Peg; NR_HOLES] {
+        std::array::from_fn(|id| Peg {
+            coord: Coord::ALL[self.permutation.backward(id as u8) as usize],
+            alive: self.pegs[id].alive,
+        })
+    }
+
+    /// Coordinates of all pegs that don't have any neighbouring pegs. Such a
+    /// peg can neither jump nor be jumped over in the current position.
+    pub fn isolated_pegs(&self) -> Vec<Coord> {
+        let neighbours = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+        Coord::all()
+            .into_iter()
+            .filter(|&coord| self.is_occupied(coord))
+            .filter(|&coord| {
+                neighbours.iter().all(|&(dx, dy)| {
+                    coord
+                        .shift(dx, dy)
+                        .is_none_or(|neighbour| !self.is_occupied(neighbour))
+                })
+            })
+            .collect()
+    }
+
+    /// Coordinates of all pegs that have at least one legal move.
+    pub fn movable_pegs(&self) -> Vec<Coord> {
+        let pos = self.as_position();
+        Coord::all()
+            .into_iter()
+            .filter(|&coord| pos.moves_from(coord).next().is_some())
+            .collect()
     }
 }
 
@@ -156,4 +209,101 @@ mod tests {
         let pos = Arrangement::new().as_position();
         assert_eq!(pos, Position::default_start());
     }
+
+    #[test]
+    fn test_from_position() {
+        let pos = Position::default_end();
+        assert_eq!(Arrangement::from_position(pos).as_position(), pos);
+    }
+
+    #[test]
+    fn test_peg_id_follows_moved_peg() {
+        let mut a = Arrangement::new();
+        let src = Coord::new(2, 0).unwrap();
+        let middle = Coord::new(1, 0).unwrap();
+        let dst = Coord::new(0, 0).unwrap();
+
+        let id = a.peg_id_at(src).unwrap();
+        assert_eq!(a.peg_id_at(dst), None);
+
+        a.perform_move(Move::from_coords(src, dst).unwrap(), Direction::Forward)
+            .unwrap();
+
+        assert_eq!(a.peg_id_at(dst), Some(id));
+        assert_eq!(a.peg_id_at(src), None);
+        assert_eq!(a.peg_id_at(middle), None);
+        assert_eq!(a.pegs()[id as usize].coord, dst);
+    }
+
+    #[test]
+    fn test_cached_pegs_match_permutation() {
+        let mut a = Arrangement::new();
+        a.toggle_hole(Coord::new(-1, -3).unwrap());
+        let moves = [
+            (Move::from_raw_coords((2, 0), (0, 0)), Direction::Forward),
+            (Move::from_raw_coords((1, 2), (1, 0)), Direction::Forward),
+            (Move::from_raw_coords((1, 2), (1, 0)), Direction::Backward),
+            (Move::from_raw_coords((-1, 0), (1, 0)), Direction::Forward),
+        ];
+
+        let mut pos = a.as_position();
+        for (mv, dir) in moves {
+            a.perform_move(mv, dir).unwrap();
+            pos = match dir {
+                Direction::Forward => pos.apply_move(mv),
+                Direction::Backward => pos.apply_move_inverse(mv),
+            };
+
+            let pegs = a.recompute_pegs();
+            assert_eq!(a.pegs(), pegs);
+            let fresh = pegs
+                .iter()
+                .filter(|peg| peg.alive)
+                .fold(0, |bits, peg| bits | peg.coord.bitmask());
+            assert_eq!(a.as_position(), Position(fresh));
+            assert_eq!(a.as_position(), pos);
+        }
+
+        // A failed move leaves the caches untouched.
+        let before = a;
+        assert!(
+            a.perform_move(Move::from_raw_coords((2, 0), (0, 0)), Direction::Forward)
+                .is_err()
+        );
+        assert_eq!(a, before);
+    }
+
+    #[test]
+    fn test_isolated_corner_peg() {
+        let pos = Position::from_ascii([
+            "    #..    ",
+            "    ...    ",
+            "  .......  ",
+            "  ..##...  ",
+            "  .......  ",
+            "    .#.    ",
+            "    .#.    ",
+        ]);
+        let a = Arrangement::from_position(pos);
+
+        assert_eq!(a.isolated_pegs(), vec![Coord::new(-1, -3).unwrap()]);
+        assert_eq!(Arrangement::new().isolated_pegs(), vec![]);
+    }
+
+    #[test]
+    fn test_movable_pegs_match_legal_move_sources() {
+        let mut a = Arrangement::new();
+        a.perform_move(Move::from_raw_coords((2, 0), (0, 0)), Direction::Forward)
+            .unwrap();
+        a.perform_move(Move::from_raw_coords((1, 2), (1, 0)), Direction::Forward)
+            .unwrap();
+
+        for arrangement in [Arrangement::new(), a] {
+            let pos = arrangement.as_position();
+            let mut sources: Vec<_> = pos.legal_moves().map(|mv| mv.source()).collect();
+            sources.sort_by_key(|coord| coord.hole_idx());
+            sources.dedup();
+            assert_eq!(arrangement.movable_pegs(), sources);
+        }
+    }
 }
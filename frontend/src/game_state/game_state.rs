@@ -1,15 +1,23 @@
 use std::rc::Rc;
 
-use common::{BloomFilter, Direction, Move, NR_HOLES, Position, coord::Coord};
+use anyhow::{Context, anyhow, ensure};
+use common::{
+    BloomFilter, Direction, Move, NR_HOLES, NR_PEGS, Position, SolutionCount, SolveResult,
+    coord::Coord,
+    count_solutions,
+    replay::{Replay, ReplayEntry, ReplayError, load_replay, save_replay},
+    solve_with_bloom_filter,
+};
+use serde::{Deserialize, Serialize};
 use yew::Reducible;
 
 use crate::game_state::{
-    Solvability,
+    Solvability, SolveTimeline,
     arrangement::{Arrangement, Peg},
     solver::SolvePath,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Mode {
     Play,
     Edit,
@@ -17,13 +25,75 @@ pub enum Mode {
 
 #[derive(Debug, Clone)]
 pub enum GameAction {
-    ClickHole { coord: Coord },
-    SetMode { mode: Mode },
+    ClickHole {
+        coord: Coord,
+    },
+    SetMode {
+        mode: Mode,
+    },
     Reset,
     Undo,
     Redo,
-    RegisterSolver { solver: Rc<BloomFilter> },
-    StepSolution { dir: Direction },
+    RegisterSolver {
+        solver: Rc<BloomFilter>,
+    },
+    /// Register a filter containing the positions that are reachable from the
+    /// default start position.
+    RegisterReachabilityFilter {
+        filter: Rc<BloomFilter>,
+    },
+    StepSolution {
+        dir: Direction,
+    },
+    Scramble {
+        peg_count: i32,
+        seed: u64,
+    },
+    /// Run the solver on the current position. Moves and edits don't do this
+    /// by themselves, so that clicking through positions never waits for the
+    /// solver.
+    Analyze,
+    /// If the game was started from an imported position without any
+    /// history, solve backward to the default start and fill the history
+    /// with that path, so that undo leads back to the start.
+    ReconstructHistory,
+}
+
+/// Everything needed to continue a game elsewhere, i.e. the start position,
+/// the history and the mode. The filters are not part of the snapshot, they
+/// have to be registered again after restoring it.
+///
+/// The history is stored in the format of [`common::replay`], so restoring
+/// a snapshot checks again that every move in it is legal.
+// Nothing in the app stores snapshots yet, they are only the building block
+// for resuming a game.
+#[cfg_attr(not(test), allow(dead_code))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    replay: Vec<u8>,
+    mode: Mode,
+}
+
+/// A game in a readable format for sharing it together with commentary, see
+/// [`GameState::to_json`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct AnnotatedGame {
+    /// Start position in the compact format of share links, see
+    /// [`Position::to_compact_string`].
+    start: String,
+    moves: Vec<AnnotatedMove>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct AnnotatedMove {
+    /// The jump in the notation of [`Move::to_notation`], e.g. `d6-d4`.
+    #[serde(rename = "move")]
+    notation: String,
+    /// Set for steps backward through a solution, which undo the jump.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    backward: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
 }
 
 /// Game State as seen from the user interface. The interaction with this state
@@ -33,12 +103,22 @@ pub enum GameAction {
 pub struct GameState {
     history: Vec<HistoryEntry>,
     redo: Vec<HistoryEntry>,
+    /// Maximum number of entries kept in `history`, if any. The oldest
+    /// entries are dropped first, so they can no longer be undone.
+    history_limit: Option<usize>,
     solve_path: SolvePath,
     arrangement: Arrangement,
     selection: Option<Coord>,
     pub mode: Mode,
     has_made_first_move: bool,
     bloom_filter: Option<Rc<BloomFilter>>,
+    reachable_filter: Option<Rc<BloomFilter>>,
+    /// Has the position or the set of filters changed since the solve path
+    /// was last updated?
+    needs_analysis: bool,
+    /// Has a solver been registered since the last attempt to reconstruct
+    /// the history?
+    needs_history_reconstruction: bool,
 }
 
 impl GameState {
@@ -48,25 +128,192 @@ impl GameState {
         Self {
             history: vec![],
             redo: vec![],
+            history_limit: None,
             solve_path: SolvePath::new(arrangement.as_position()),
             arrangement,
             selection: None,
             mode: Mode::Play,
             has_made_first_move: false,
             bloom_filter: None,
+            reachable_filter: None,
+            needs_analysis: true,
+            needs_history_reconstruction: false,
+        }
+    }
+
+    /// Start a new game from the given position, with an empty history.
+    pub fn from_position(pos: Position) -> GameState {
+        let arrangement = Arrangement::from_position(pos);
+
+        Self {
+            solve_path: SolvePath::new(pos),
+            arrangement,
+            ..Self::new()
+        }
+    }
+
+    /// Only keep the given number of most recent history entries, or all of
+    /// them if the limit is `None`.
+    pub fn with_history_limit(mut self, limit: Option<usize>) -> GameState {
+        self.history_limit = limit;
+        self.trim_history();
+        self
+    }
+
+    /// Add an entry to the history, dropping the oldest entry if the history
+    /// limit is exceeded.
+    fn push_history(&mut self, entry: HistoryEntry) {
+        self.history.push(entry);
+        self.trim_history();
+    }
+
+    fn trim_history(&mut self) {
+        if let Some(limit) = self.history_limit {
+            let excess = self.history.len().saturating_sub(limit);
+            self.history.drain(..excess);
+        }
+    }
+
+    /// Capture the current game, see [`Snapshot`]. The redo stack and the
+    /// selection are not included.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn to_snapshot(&self) -> Snapshot {
+        // Walk back through the history to find the start position. Edits
+        // store the arrangement from before the edit, while the replay
+        // stores the position after it.
+        let mut arrangement = self.arrangement;
+        let mut entries = Vec::with_capacity(self.history.len());
+        for entry in self.history.iter().rev() {
+            match *entry {
+                HistoryEntry::Edit(previous) => {
+                    entries.push(ReplayEntry::Edit(arrangement.as_position()));
+                    arrangement = previous;
+                }
+                HistoryEntry::Move(mv, dir) => {
+                    if let Err(e) = arrangement.perform_move(mv, !dir) {
+                        // Keep the part of the history that can be replayed.
+                        log::error!("dropping inconsistent history: {e:#}");
+                        break;
+                    }
+                    entries.push(ReplayEntry::Move(mv, dir));
+                }
+            }
+        }
+        entries.reverse();
+
+        let replay = Replay {
+            start: arrangement.as_position(),
+            entries,
+        };
+        Snapshot {
+            replay: save_replay(&replay),
+            mode: self.mode,
+        }
+    }
+
+    /// Restore a game captured by [`GameState::to_snapshot`].
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn from_snapshot(snapshot: &Snapshot) -> Result<GameState, ReplayError> {
+        let replay = load_replay(&snapshot.replay)?;
+
+        let mut state = GameState::from_position(replay.start);
+        state.mode = snapshot.mode;
+        for entry in replay.entries {
+            match entry {
+                ReplayEntry::Edit(pos) => {
+                    state.push_history(HistoryEntry::Edit(state.arrangement));
+                    state.arrangement = Arrangement::from_position(pos);
+                    state.solve_path = SolvePath::new(pos);
+                }
+                ReplayEntry::Move(mv, dir) => {
+                    state
+                        .perform_move(mv, dir)
+                        .expect("load_replay checks that every move is legal");
+                    state.push_history(HistoryEntry::Move(mv, dir));
+                }
+            }
+        }
+        Ok(state)
+    }
+
+    /// Export the moves of the game as JSON, with an optional comment for
+    /// each move. `comments[i]` belongs to the `i`-th exported move, missing
+    /// entries mean no comment.
+    ///
+    /// Edits can't be written in move notation, so the exported game starts
+    /// at the position after the last edit.
+    pub fn to_json(&self, comments: &[Option<String>]) -> String {
+        let mut arrangement = self.arrangement;
+        let mut moves = vec![];
+        for entry in self.history.iter().rev() {
+            let HistoryEntry::Move(mv, dir) = *entry else {
+                break;
+            };
+            if let Err(e) = arrangement.perform_move(mv, !dir) {
+                log::error!("dropping inconsistent history: {e:#}");
+                break;
+            }
+            moves.push((mv, dir));
+        }
+        moves.reverse();
+
+        let game = AnnotatedGame {
+            start: arrangement.as_position().to_compact_string(),
+            moves: moves
+                .into_iter()
+                .enumerate()
+                .map(|(i, (mv, dir))| AnnotatedMove {
+                    notation: mv.to_notation(),
+                    backward: dir == Direction::Backward,
+                    comment: comments.get(i).cloned().flatten(),
+                })
+                .collect(),
+        };
+        serde_json::to_string(&game).expect("the game can always be serialized")
+    }
+
+    /// Import a game exported by [`GameState::to_json`], returning it
+    /// together with the comment of each move.
+    pub fn from_json(json: &str) -> anyhow::Result<(GameState, Vec<Option<String>>)> {
+        let game: AnnotatedGame = serde_json::from_str(json).context("invalid game JSON")?;
+        let start = Position::from_compact_string(&game.start)
+            .ok_or_else(|| anyhow!("invalid start position {:?}", game.start))?;
+        ensure!(
+            (1..=NR_PEGS as i32).contains(&start.count()),
+            "can't play a game starting with {} pegs",
+            start.count()
+        );
+
+        let mut state = GameState::from_position(start);
+        let mut comments = Vec::with_capacity(game.moves.len());
+        for (i, annotated) in game.moves.into_iter().enumerate() {
+            let mv = Move::from_notation(&annotated.notation)
+                .ok_or_else(|| anyhow!("invalid notation {:?}", annotated.notation))
+                .with_context(|| format!("move {}", i + 1))?;
+            let dir = if annotated.backward {
+                Direction::Backward
+            } else {
+                Direction::Forward
+            };
+            state
+                .perform_move(mv, dir)
+                .with_context(|| format!("move {}", i + 1))?;
+            state.push_history(HistoryEntry::Move(mv, dir));
+            comments.push(annotated.comment);
         }
+        Ok((state, comments))
     }
+
     pub fn selected_coord(&self) -> Option<Coord> {
         self.selection
     }
+    /// Identity of the peg in the selected hole, if any.
+    pub fn selected_peg_id(&self) -> Option<u8> {
+        self.selection
+            .and_then(|coord| self.arrangement.peg_id_at(coord))
+    }
     pub fn as_position(&self) -> Position {
-        let mut out = 0;
-        for p in self.pegs() {
-            if p.alive {
-                out |= p.coord.bitmask();
-            }
-        }
-        Position(out)
+        self.arrangement.as_position()
     }
     pub fn nr_pegs(&self) -> i32 {
         self.arrangement.nr_pegs() as i32
@@ -74,6 +321,14 @@ impl GameState {
     pub fn pegs(&self) -> [Peg; NR_HOLES] {
         self.arrangement.pegs()
     }
+    /// Coordinates of all pegs that have at least one legal move.
+    pub fn movable_pegs(&self) -> Vec<Coord> {
+        self.arrangement.movable_pegs()
+    }
+    /// Coordinates of all pegs without any neighbouring pegs.
+    pub fn isolated_pegs(&self) -> Vec<Coord> {
+        self.arrangement.isolated_pegs()
+    }
 
     pub fn can_undo(&self) -> bool {
         !self.history.is_empty()
@@ -91,6 +346,70 @@ impl GameState {
         self.solve_path.is_solvable()
     }
 
+    pub fn timeline(&self) -> SolveTimeline {
+        self.solve_path.timeline(self.as_position())
+    }
+
+    /// Count the solutions from the current position to the end, up to
+    /// `cap`. Returns `None` if the solver hasn't been registered yet.
+    ///
+    /// This can take a moment, so unlike the solve path it is not computed
+    /// while reducing actions.
+    pub fn count_solutions(&self, cap: u64) -> Option<SolutionCount> {
+        let filter = self.bloom_filter.as_ref()?;
+        Some(count_solutions(
+            self.as_position(),
+            Position::default_end(),
+            filter,
+            Direction::Forward,
+            cap,
+        ))
+    }
+
+    /// Would [`GameAction::Analyze`] do anything, i.e. is a filter registered
+    /// and has the position changed since the last analysis?
+    pub fn needs_analysis(&self) -> bool {
+        self.needs_analysis && (self.bloom_filter.is_some() || self.reachable_filter.is_some())
+    }
+
+    /// Would [`GameAction::ReconstructHistory`] do anything, i.e. has a
+    /// solver been registered for a game without any history that doesn't
+    /// begin at the default start?
+    pub fn needs_history_reconstruction(&self) -> bool {
+        self.needs_history_reconstruction
+            && self.bloom_filter.is_some()
+            && self.history.is_empty()
+            && self.redo.is_empty()
+            && self.as_position() != Position::default_start()
+    }
+
+    /// Update the solve path for the current position, using whichever
+    /// filters have been registered so far.
+    fn recompute_solve_path(&mut self) {
+        let pos = self.as_position();
+        if let Some(bf) = &self.bloom_filter {
+            self.solve_path.recompute(bf, pos);
+        }
+        if let Some(filter) = &self.reachable_filter {
+            self.solve_path.rule_out_unreachable(filter, pos);
+        }
+    }
+
+    /// Perform a move on the board and keep the solve path in sync. Fails
+    /// without changing anything if the move isn't legal on the board.
+    ///
+    /// If the solve path can't follow the move, then it is out of sync with
+    /// the board, so it is rebuilt from scratch instead of aborting the app.
+    fn perform_move(&mut self, mv: Move, dir: Direction) -> anyhow::Result<()> {
+        self.arrangement.perform_move(mv, dir)?;
+        if let Err(e) = self.solve_path.apply_move(mv, dir) {
+            log::error!("resetting the solve path: {e:#}");
+            self.solve_path = SolvePath::new(self.as_position());
+        }
+        self.needs_analysis = true;
+        Ok(())
+    }
+
     /// Check some properties that should always hold
     #[cfg(test)]
     fn validate(&self) {
@@ -147,17 +466,11 @@ impl Reducible for GameState {
                         };
 
                         let mut state = (*self).clone();
-                        match state.arrangement.perform_move(mv, Direction::Forward) {
+                        match state.perform_move(mv, Direction::Forward) {
                             Ok(_) => {
                                 // successfully made a move
                                 state.has_made_first_move = true;
-                                state
-                                    .history
-                                    .push(HistoryEntry::Move(mv, Direction::Forward));
-                                state.solve_path.apply_move(mv, Direction::Forward);
-                                if let Some(bf) = &self.bloom_filter {
-                                    state.solve_path.recompute(bf, state.as_position());
-                                }
+                                state.push_history(HistoryEntry::Move(mv, Direction::Forward));
                                 state.redo.clear();
                                 state.selection = None;
                             }
@@ -180,9 +493,7 @@ impl Reducible for GameState {
 
                 state.arrangement.toggle_hole(coord);
                 state.solve_path = SolvePath::new(state.as_position());
-                if let Some(bf) = &self.bloom_filter {
-                    state.solve_path.recompute(bf, state.as_position());
-                }
+                state.needs_analysis = true;
 
                 // If the last history entry already contains an edit, then we
                 // don't append another entry. This has the effect of combining
@@ -190,7 +501,7 @@ impl Reducible for GameState {
                 // TODO: add an edit session id or something like that, so that
                 // we can have one undo step per edit session.
                 if !matches!(self.history.last(), Some(&HistoryEntry::Edit(_))) {
-                    state.history.push(HistoryEntry::Edit(old_arrangement));
+                    state.push_history(HistoryEntry::Edit(old_arrangement));
                 }
                 state.redo.clear();
                 state.into()
@@ -210,17 +521,16 @@ impl Reducible for GameState {
                         std::mem::swap(&mut state.arrangement, &mut arrangement);
                         state.redo.push(HistoryEntry::Edit(arrangement));
                         state.solve_path = SolvePath::new(state.as_position());
-                        if let Some(bf) = &self.bloom_filter {
-                            state.solve_path.recompute(bf, state.as_position());
-                        }
+                        state.needs_analysis = true;
                     }
                     HistoryEntry::Move(mv, dir) => {
-                        state.redo.push(HistoryEntry::Move(mv, dir));
-                        state.arrangement.perform_move(mv, !dir).unwrap();
-                        state.solve_path.apply_move(mv, !dir);
-                        if let Some(bf) = &self.bloom_filter {
-                            state.solve_path.recompute(bf, state.as_position());
+                        if let Err(e) = state.perform_move(mv, !dir) {
+                            // Drop the entry, so that the history behind it
+                            // stays reachable.
+                            log::error!("ignoring inconsistent history entry: {e:#}");
+                            return state.into();
                         }
+                        state.redo.push(HistoryEntry::Move(mv, dir));
                     }
                 }
 
@@ -239,48 +549,54 @@ impl Reducible for GameState {
                 match entry {
                     HistoryEntry::Edit(mut arrangement) => {
                         std::mem::swap(&mut state.arrangement, &mut arrangement);
-                        state.history.push(HistoryEntry::Edit(arrangement));
+                        state.push_history(HistoryEntry::Edit(arrangement));
                         state.solve_path = SolvePath::new(state.as_position());
-                        if let Some(bf) = &self.bloom_filter {
-                            state.solve_path.recompute(bf, state.as_position());
-                        }
+                        state.needs_analysis = true;
                     }
                     HistoryEntry::Move(mv, dir) => {
-                        state.history.push(HistoryEntry::Move(mv, dir));
-                        state.arrangement.perform_move(mv, dir).unwrap();
-                        state.solve_path.apply_move(mv, dir);
-                        if let Some(bf) = &self.bloom_filter {
-                            state.solve_path.recompute(bf, state.as_position());
+                        if let Err(e) = state.perform_move(mv, dir) {
+                            log::error!("ignoring inconsistent redo entry: {e:#}");
+                            return state.into();
                         }
+                        state.push_history(HistoryEntry::Move(mv, dir));
                     }
                 }
 
                 state.into()
             }
             (GameAction::Reset, _) => {
-                let mut state = GameState::new();
+                let mut state = GameState::new().with_history_limit(self.history_limit);
                 state.has_made_first_move = self.has_made_first_move;
                 state.bloom_filter = self.bloom_filter.clone();
+                state.reachable_filter = self.reachable_filter.clone();
                 state.into()
             }
             (GameAction::RegisterSolver { solver }, _) => {
-                // todo: maybe add a way to disable the solver while we're not
-                // showing the solver toolbar?
                 let mut state = (*self).clone();
-                state.solve_path.recompute(&solver, state.as_position());
                 state.bloom_filter = Some(solver);
+                state.needs_analysis = true;
+                state.needs_history_reconstruction = true;
+                state.into()
+            }
+            (GameAction::RegisterReachabilityFilter { filter }, _) => {
+                let mut state = (*self).clone();
+                state.reachable_filter = Some(filter);
+                state.needs_analysis = true;
                 state.into()
             }
             (GameAction::StepSolution { dir }, _) => {
                 if let Some(mv) = self.solve_path.next_move(dir) {
                     let mut state = (*self).clone();
-                    state.history.push(HistoryEntry::Move(mv, dir));
-                    state.redo.clear();
-                    state.arrangement.perform_move(mv, dir).unwrap();
-                    state.solve_path.apply_move(mv, dir);
-                    if let Some(bf) = &self.bloom_filter {
-                        state.solve_path.recompute(bf, state.as_position());
+                    if let Err(e) = state.perform_move(mv, dir) {
+                        // The solve path doesn't match the board, so start over
+                        // with a fresh one.
+                        log::error!("ignoring inconsistent solution step: {e:#}");
+                        state.solve_path = SolvePath::new(state.as_position());
+                        state.needs_analysis = true;
+                        return state.into();
                     }
+                    state.push_history(HistoryEntry::Move(mv, dir));
+                    state.redo.clear();
                     state.selection = None;
 
                     state.into()
@@ -288,6 +604,53 @@ impl Reducible for GameState {
                     self
                 }
             }
+            (GameAction::Scramble { peg_count, seed }, _) => {
+                // Replace the board with a random solvable position, starting
+                // over with an empty history.
+                let mut state =
+                    GameState::from_position(Position::random_solvable(peg_count, seed))
+                        .with_history_limit(self.history_limit);
+                state.mode = self.mode;
+                state.has_made_first_move = self.has_made_first_move;
+                state.bloom_filter = self.bloom_filter.clone();
+                state.reachable_filter = self.reachable_filter.clone();
+                state.needs_analysis = true;
+                state.into()
+            }
+            (GameAction::Analyze, _) => {
+                if !self.needs_analysis() {
+                    return self;
+                }
+
+                let mut state = (*self).clone();
+                state.recompute_solve_path();
+                state.needs_analysis = false;
+                state.into()
+            }
+            (GameAction::ReconstructHistory, _) => {
+                if !self.needs_history_reconstruction() {
+                    return self;
+                }
+                let Some(bf) = &self.bloom_filter else {
+                    return self;
+                };
+
+                // Only try once per solver, even if no path is found.
+                let mut state = (*self).clone();
+                state.needs_history_reconstruction = false;
+
+                // The backward solution undoes the moves starting with the
+                // last one, so the history is the solution in reverse.
+                let SolveResult::Solved(moves) =
+                    solve_with_bloom_filter(self.as_position(), bf, Direction::Backward, 0).0
+                else {
+                    return state.into();
+                };
+                for &mv in moves.iter().rev() {
+                    state.push_history(HistoryEntry::Move(mv, Direction::Forward));
+                }
+                state.into()
+            }
             (GameAction::SetMode { mode }, _) => {
                 if mode == self.mode {
                     return self;
@@ -310,6 +673,7 @@ enum HistoryEntry {
 
 #[cfg(test)]
 mod tests {
+    use common::debruijn::de_bruijn_solvable;
     use proptest::prelude::*;
 
     use super::*;
@@ -325,8 +689,7 @@ mod tests {
     fn game_state_after_one_move() -> Rc<GameState> {
         let gs = game_state();
         let gs = gs.reduce(click_action(2, 0));
-        let gs = gs.reduce(click_action(0, 0));
-        gs
+        gs.reduce(click_action(0, 0))
     }
 
     #[test]
@@ -483,12 +846,301 @@ mod tests {
         assert!(gs.has_made_first_move());
     }
 
+    #[test]
+    fn test_scramble() {
+        let gs = game_state_after_one_move().reduce(GameAction::Scramble {
+            peg_count: 12,
+            seed: 3,
+        });
+
+        assert!(de_bruijn_solvable(gs.as_position()));
+        assert_eq!(gs.nr_pegs(), 12);
+        assert!(!gs.can_undo());
+    }
+
+    #[test]
+    fn test_from_position() {
+        let gs = GameState::from_position(Position::default_end());
+        assert_eq!(gs.as_position(), Position::default_end());
+        assert_eq!(gs.nr_pegs(), 1);
+        assert!(!gs.can_undo());
+    }
+
     #[test]
     fn test_nr_pegs() {
         assert_eq!(game_state().nr_pegs(), 32);
         assert_eq!(game_state_after_one_move().nr_pegs(), 31);
     }
 
+    #[test]
+    fn test_reachability_filter_rules_out_unreachable_positions() {
+        // Emptying a corner hole instead of the centre is a parity violation,
+        // so this position can't be reached from the start.
+        let pos = Position::from_ascii([
+            "    .##    ",
+            "    ###    ",
+            "  #######  ",
+            "  #######  ",
+            "  #######  ",
+            "    ###    ",
+            "    ###    ",
+        ]);
+        let empty_filter = Rc::new(BloomFilter::new(13, 1));
+
+        let gs = Rc::new(GameState::from_position(pos));
+        assert_eq!(gs.is_solvable().0, Solvability::Unknown);
+        let gs = gs.reduce(GameAction::RegisterReachabilityFilter {
+            filter: empty_filter.clone(),
+        });
+        assert_eq!(gs.is_solvable().0, Solvability::Unknown);
+        let gs = gs.reduce(GameAction::Analyze);
+        assert_eq!(gs.is_solvable().0, Solvability::Unsolvable);
+
+        let mut filter = BloomFilter::new(13, 1);
+        filter.insert(pos.normalize());
+        let gs = Rc::new(GameState::from_position(pos))
+            .reduce(GameAction::RegisterReachabilityFilter {
+                filter: Rc::new(filter),
+            })
+            .reduce(GameAction::Analyze);
+        assert_eq!(gs.is_solvable().0, Solvability::Unknown);
+
+        // The start itself is always reachable, regardless of the filter.
+        let gs = game_state()
+            .reduce(GameAction::RegisterReachabilityFilter {
+                filter: empty_filter,
+            })
+            .reduce(GameAction::Analyze);
+        assert_eq!(gs.is_solvable().0, Solvability::Solved);
+    }
+
+    #[test]
+    fn test_analysis_is_deferred() {
+        let pos = game_state_after_one_move().as_position();
+
+        // Without any filter there is nothing to analyze.
+        let gs = Rc::new(GameState::from_position(pos));
+        assert!(!gs.needs_analysis());
+        let analyzed = gs.clone().reduce(GameAction::Analyze);
+        assert!(Rc::ptr_eq(&gs, &analyzed));
+
+        let mut filter = BloomFilter::new(13, 1);
+        filter.insert(pos.normalize());
+        let gs = gs.reduce(GameAction::RegisterSolver {
+            solver: Rc::new(filter),
+        });
+        assert!(gs.needs_analysis());
+        assert_eq!(gs.is_solvable().0, Solvability::Unknown);
+
+        let gs = gs.reduce(GameAction::Analyze);
+        assert!(!gs.needs_analysis());
+        assert_eq!(gs.is_solvable().0, Solvability::Solvable);
+
+        // Analyzing again without any change is a no-op.
+        let analyzed = gs.clone().reduce(GameAction::Analyze);
+        assert!(Rc::ptr_eq(&gs, &analyzed));
+
+        // Moves only mark the state as stale.
+        let gs = gs.reduce(click_action(1, 2)).reduce(click_action(1, 0));
+        assert_eq!(gs.nr_pegs(), 30);
+        assert!(gs.needs_analysis());
+    }
+
+    #[test]
+    fn test_history_limit() {
+        let mut gs = Rc::new(GameState::new().with_history_limit(Some(2)));
+        let moves = [((0, 2), (0, 0)), ((-2, 1), (0, 1)), ((-1, -1), (-1, 1))];
+        let mut positions = vec![gs.as_position()];
+        for ((x1, y1), (x2, y2)) in moves {
+            gs = gs.reduce(click_action(x1, y1)).reduce(click_action(x2, y2));
+            positions.push(gs.as_position());
+        }
+        assert_eq!(gs.nr_pegs(), 29);
+
+        // Only the last two moves can be undone.
+        gs = gs.reduce(GameAction::Undo);
+        assert_eq!(gs.as_position(), positions[2]);
+        gs = gs.reduce(GameAction::Undo);
+        assert_eq!(gs.as_position(), positions[1]);
+        assert!(!gs.can_undo());
+
+        // Redoing and resetting keeps the limit.
+        gs = gs.reduce(GameAction::Redo).reduce(GameAction::Redo);
+        assert_eq!(gs.as_position(), positions[3]);
+        let gs = gs.reduce(GameAction::Reset);
+        assert_eq!(gs.history_limit, Some(2));
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let gs = Rc::new(GameState::from_position(Position::default_end()));
+        assert_eq!(gs.count_solutions(1000), None);
+
+        let filter = Rc::new(BloomFilter::accept_all());
+        let gs = gs.reduce(GameAction::RegisterSolver {
+            solver: filter.clone(),
+        });
+        assert_eq!(gs.count_solutions(1000), Some(SolutionCount::Exact(1)));
+
+        // Without a real filter, the full board is too much to count, but a
+        // scrambled board with a dozen pegs already has plenty of solutions.
+        let gs = game_state()
+            .reduce(GameAction::RegisterSolver { solver: filter })
+            .reduce(GameAction::Scramble {
+                peg_count: 12,
+                seed: 0,
+            });
+        assert_eq!(gs.count_solutions(1000), Some(SolutionCount::AtLeast(1000)));
+        assert_eq!(gs.count_solutions(10), Some(SolutionCount::AtLeast(10)));
+    }
+
+    #[test]
+    fn test_inconsistent_moves_are_ignored() {
+        let gs = game_state_after_one_move();
+        let pos = gs.as_position();
+
+        // Undoing this move would need a hole at (0, -2), which is occupied.
+        let mut corrupted = (*gs).clone();
+        corrupted.history.push(HistoryEntry::Move(
+            Move::from_raw_coords((0, -2), (0, 0)),
+            Direction::Forward,
+        ));
+        let gs = Rc::new(corrupted).reduce(GameAction::Undo);
+        gs.validate();
+        assert_eq!(gs.as_position(), pos);
+        assert!(!gs.can_redo());
+
+        // The entry is dropped, so the rest of the history is still usable.
+        let gs = gs.reduce(GameAction::Undo);
+        assert_eq!(gs.as_position(), Position::default_start());
+
+        // A solve path that belongs to a different position.
+        let mut corrupted = (*gs).clone();
+        corrupted.solve_path = SolvePath::new(Position::default_end());
+        let gs = Rc::new(corrupted).reduce(GameAction::StepSolution {
+            dir: Direction::Backward,
+        });
+        assert_eq!(gs.as_position(), Position::default_start());
+        assert_eq!(
+            gs.is_solvable(),
+            (Solvability::Solved, Solvability::Solvable)
+        );
+    }
+
+    #[test]
+    fn test_reconstruct_history() {
+        let mut path = vec![Position::default_start()];
+        for _ in 0..10 {
+            let pos = *path.last().unwrap();
+            let mv = common::all_moves()
+                .into_iter()
+                .find(|&mv| pos.can_move(mv))
+                .unwrap();
+            path.push(pos.apply_move(mv));
+        }
+        let imported = *path.last().unwrap();
+
+        // Solving backward searches through the inverse positions, but the
+        // initial check looks at the position itself.
+        let mut filter = BloomFilter::new(100_003, 1);
+        for pos in &path {
+            filter.insert(pos.normalize());
+            filter.insert(pos.inverse().normalize());
+        }
+
+        let mut gs = Rc::new(GameState::from_position(imported));
+        assert!(!gs.can_undo());
+        assert!(!gs.needs_history_reconstruction());
+        gs = gs.reduce(GameAction::RegisterSolver {
+            solver: Rc::new(filter),
+        });
+        assert!(gs.needs_history_reconstruction());
+        gs = gs.reduce(GameAction::ReconstructHistory);
+        assert!(!gs.needs_history_reconstruction());
+        assert_eq!(gs.as_position(), imported);
+
+        for _ in 0..10 {
+            assert!(gs.can_undo());
+            gs = gs.reduce(GameAction::Undo);
+            gs.validate();
+        }
+        assert!(!gs.can_undo());
+        assert_eq!(gs.as_position(), Position::default_start());
+
+        // Once there is a history, it is left alone.
+        let redone = gs.clone().reduce(GameAction::ReconstructHistory);
+        assert!(Rc::ptr_eq(&gs, &redone));
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let gs = game_state_after_one_move()
+            .reduce(GameAction::SetMode { mode: Mode::Edit })
+            .reduce(click_action(0, 2))
+            .reduce(GameAction::SetMode { mode: Mode::Play })
+            .reduce(click_action(1, 2))
+            .reduce(click_action(1, 0));
+
+        let restored = GameState::from_snapshot(&gs.to_snapshot()).unwrap();
+        assert_eq!(restored.as_position(), gs.as_position());
+        assert_eq!(restored.nr_pegs(), gs.nr_pegs());
+        assert_eq!(restored.mode, Mode::Play);
+
+        // Undoing the restored game leads back to the start.
+        let mut restored = Rc::new(restored);
+        for _ in 0..3 {
+            restored = restored.reduce(GameAction::Undo);
+        }
+        assert!(!restored.can_undo());
+        assert_eq!(restored.as_position(), Position::default_start());
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let gs = game_state_after_one_move()
+            .reduce(click_action(1, 2))
+            .reduce(click_action(1, 0))
+            .reduce(click_action(-1, 1))
+            .reduce(click_action(1, 1));
+        let comments = vec![
+            Some("the usual opening".to_string()),
+            None,
+            Some("back towards the centre".to_string()),
+        ];
+
+        let json = gs.to_json(&comments);
+        assert!(json.contains(r#""start":"1fffeffff""#));
+        assert!(json.contains(r#""move":"f4-d4""#));
+
+        let (restored, restored_comments) = GameState::from_json(&json).unwrap();
+        assert_eq!(restored.as_position(), gs.as_position());
+        assert_eq!(restored.history, gs.history);
+        assert_eq!(restored_comments, comments);
+        assert_eq!(restored.to_json(&restored_comments), json);
+
+        // The exported game starts after the last edit.
+        let edited = gs
+            .reduce(GameAction::SetMode { mode: Mode::Edit })
+            .reduce(click_action(0, 3))
+            .reduce(GameAction::SetMode { mode: Mode::Play });
+        let (restored, restored_comments) = GameState::from_json(&edited.to_json(&[])).unwrap();
+        assert_eq!(restored.as_position(), edited.as_position());
+        assert!(!restored.can_undo());
+        assert!(restored_comments.is_empty());
+
+        assert!(
+            GameState::from_json(r#"{"start":"1fffeffff","moves":[{"move":"d4-d6"}]}"#).is_err()
+        );
+        assert!(GameState::from_json(r#"{"start":"xyz","moves":[]}"#).is_err());
+        // Empty and full boards can't be played.
+        assert_eq!(
+            Position::from_compact_string("1ffffffff").map(|p| p.count()),
+            Some(NR_PEGS as i32 + 1)
+        );
+        assert!(GameState::from_json(r#"{"start":"000000000","moves":[]}"#).is_err());
+        assert!(GameState::from_json(r#"{"start":"1ffffffff","moves":[]}"#).is_err());
+    }
+
     fn action_sequence(max_len: usize) -> impl Strategy<Value = Vec<GameAction>> {
         let click_strategy = (-2..=2i8, -2..2i8).prop_filter_map("", |(x, y)| {
             Some(ClickHole {
@@ -513,6 +1165,7 @@ mod tests {
             Just(GameAction::Reset),
             Just(Undo),
             Just(Redo),
+            Just(Analyze),
             step_strategy,
             click_strategy,
         ];
@@ -532,6 +1185,9 @@ mod tests {
                 gs = gs.reduce(action);
                 gs.validate();
             }
+
+            let restored = GameState::from_snapshot(&gs.to_snapshot()).unwrap();
+            assert_eq!(restored.as_position(), gs.as_position());
         }
     }
 
@@ -1,8 +1,11 @@
 mod arrangement;
+#[allow(clippy::module_inception)]
 mod game_state;
 mod permutation;
 mod solver;
+mod timeline;
 
 pub use arrangement::Peg;
+pub use common::Solvability;
 pub use game_state::{GameAction, GameState, Mode};
-pub use solver::Solvability;
+pub use timeline::SolveTimeline;
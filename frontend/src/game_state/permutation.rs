@@ -18,6 +18,7 @@ impl<const N: usize> Permutation<N> {
         self.forward[pos as usize]
     }
 
+    #[cfg(test)]
     pub fn backward(&self, pos: u8) -> u8 {
         self.backward[pos as usize]
     }
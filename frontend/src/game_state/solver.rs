@@ -1,7 +1,11 @@
+use anyhow::ensure;
 use common::{
-    BloomFilter, Direction, Move, NR_PEGS, Position, SolveResult, solve_with_bloom_filter,
+    BloomFilter, Direction, Move, NR_PEGS, Position, Solvability, SolveResult,
+    solve_with_bloom_filter,
 };
 
+use crate::game_state::SolveTimeline;
+
 /// Store the path to solve the current position.
 ///
 /// This data structure updates the known solve path if a move is taken. If
@@ -16,6 +20,12 @@ pub struct SolvePath {
     backward: Solvability,
 
     current_nr_pegs: i32,
+
+    /// After leaving the known path with a move that appears further along
+    /// the path, the old path without that move might still work. This holds
+    /// the direction and the candidate moves in path order until the next
+    /// recompute checks them.
+    repair: Option<(Direction, Vec<Move>)>,
 }
 
 impl SolvePath {
@@ -46,6 +56,7 @@ impl SolvePath {
             forward,
             backward,
             current_nr_pegs,
+            repair: None,
         }
     }
 
@@ -74,13 +85,26 @@ impl SolvePath {
     /// If the move follows the next move that was already suggested by the
     /// solver, then the solver can keep the current solve path cached and
     /// doesn't have to recompute anything.
-    pub fn apply_move(&mut self, mv: Move, dir: Direction) {
+    ///
+    /// Fails without changing anything if the move would leave the range of
+    /// peg counts that the path can describe, which means that the solve path
+    /// is out of sync with the board.
+    pub fn apply_move(&mut self, mv: Move, dir: Direction) -> anyhow::Result<()> {
+        let nr_pegs = self.current_nr_pegs
+            + match dir {
+                Direction::Forward => -1,
+                Direction::Backward => 1,
+            };
+        ensure!(
+            (1..=NR_PEGS as i32).contains(&nr_pegs),
+            "move {} would leave {nr_pegs} pegs on the board",
+            mv.to_notation()
+        );
+
         let next_move = self.next_move(dir);
-        self.current_nr_pegs += match dir {
-            Direction::Forward => -1,
-            Direction::Backward => 1,
-        };
-        assert!((1..=NR_PEGS as i32).contains(&self.current_nr_pegs));
+        let repair = self.path_without(mv, dir);
+        self.repair = None;
+        self.current_nr_pegs = nr_pegs;
 
         if next_move == Some(mv) {
             // we moved along the known path
@@ -105,6 +129,7 @@ impl SolvePath {
             }
         } else {
             // we left the last computed solve path
+            self.repair = repair.map(|moves| (dir, moves));
             match dir {
                 Direction::Forward => {
                     self.forward = Solvability::Unknown;
@@ -117,6 +142,71 @@ impl SolvePath {
                 }
             }
         }
+        Ok(())
+    }
+
+    /// The known path in the given direction with `mv` taken out, if `mv` is
+    /// part of it. The moves are in path order, i.e. in forward direction.
+    fn path_without(&self, mv: Move, dir: Direction) -> Option<Vec<Move>> {
+        let idx = self.get_index_in_direction(dir)?;
+        let remaining = match dir {
+            Direction::Forward if self.forward.solvable() => &self.path[idx..],
+            Direction::Backward if self.backward.solvable() => &self.path[..=idx],
+            _ => return None,
+        };
+        let mv_idx = remaining.iter().position(|&m| m == mv)?;
+        let mut moves = remaining.to_vec();
+        moves.remove(mv_idx);
+        Some(moves)
+    }
+
+    /// Check if the repair candidate leads from `pos` to the end of the game
+    /// in its direction, and if so, store it as the new path.
+    fn try_repair(&mut self, pos: Position) {
+        let Some((dir, moves)) = self.repair.take() else {
+            return;
+        };
+
+        let is_valid = match dir {
+            Direction::Forward => {
+                let mut p = pos;
+                moves.iter().all(|&mv| {
+                    let ok = p.can_move(mv);
+                    if ok {
+                        p = p.apply_move(mv);
+                    }
+                    ok
+                }) && p == Position::default_end()
+            }
+            Direction::Backward => {
+                let mut p = pos;
+                moves.iter().rev().all(|&mv| {
+                    let ok = p.can_move_inverse(mv);
+                    if ok {
+                        p = p.apply_move_inverse(mv);
+                    }
+                    ok
+                }) && p == Position::default_start()
+            }
+        };
+        if !is_valid {
+            return;
+        }
+
+        let Some(idx) = self.get_index_in_direction(dir) else {
+            return;
+        };
+        match dir {
+            Direction::Forward if self.forward == Solvability::Unknown => {
+                self.path[idx..].copy_from_slice(&moves);
+                self.forward = Solvability::Solvable;
+            }
+            Direction::Backward if self.backward == Solvability::Unknown => {
+                self.path[..=idx].copy_from_slice(&moves);
+                self.backward = Solvability::Solvable;
+            }
+            _ => {}
+        }
     }
 
     /// After a move has been made away from a known solvable position, then we
@@ -163,61 +253,101 @@ impl SolvePath {
         }
     }
 
-    /// Recompute the solution path if needed.
+    /// List the positions on the known solve path through `pos`, which has to
+    /// be the position that the SolvePath state is in.
+    pub fn timeline(&self, pos: Position) -> SolveTimeline {
+        assert_eq!(pos.count(), self.current_nr_pegs);
+
+        let mut before = vec![];
+        if self.backward.solvable() {
+            let mut p = pos;
+            if let Some(idx) = self.get_index_in_direction(Direction::Backward) {
+                for &mv in self.path[..=idx].iter().rev() {
+                    p = p.apply_move_inverse(mv);
+                    before.push(p);
+                }
+            }
+            before.reverse();
+        }
+
+        let mut after = vec![];
+        if self.forward.solvable() {
+            let mut p = pos;
+            if let Some(idx) = self.get_index_in_direction(Direction::Forward) {
+                for &mv in &self.path[idx..] {
+                    p = p.apply_move(mv);
+                    after.push(p);
+                }
+            }
+        }
+
+        SolveTimeline::new(before, pos, after, self.backward, self.forward)
+    }
+
+    /// Recompute the solution path if needed. If the last move left the path
+    /// but the rest of the old path still works, that is used instead of
+    /// running the solver.
     ///
     /// The given position must correspond to the position that the SolvePath
-    /// state is already in.
-    pub fn recompute(&mut self, bloom_filter: &BloomFilter, pos: Position) {
+    /// state is already in. Returns the number of steps the solver took.
+    pub fn recompute(&mut self, bloom_filter: &BloomFilter, pos: Position) -> u32 {
         assert_eq!(pos.count(), self.current_nr_pegs);
 
+        self.try_repair(pos);
+
+        let mut nr_steps = 0;
         if self.forward == Solvability::Unknown {
-            let solve_result = solve_with_bloom_filter(pos, bloom_filter, Direction::Forward, 0).0;
-
-            match solve_result {
-                SolveResult::Solved(moves) => {
-                    match self.get_index_in_direction(Direction::Forward) {
-                        Some(idx) => {
-                            let slice = &mut self.path[idx..];
-                            slice.copy_from_slice(&moves);
-                        }
-                        None => {
-                            log::warn!(
-                                "solvability was set to Unknown even though we're at the end??"
-                            )
-                        }
+            let (solve_result, info) =
+                solve_with_bloom_filter(pos, bloom_filter, Direction::Forward, 0);
+            nr_steps += info.nr_steps;
+
+            if let SolveResult::Solved(moves) = &solve_result {
+                match self.get_index_in_direction(Direction::Forward) {
+                    Some(idx) => {
+                        let slice = &mut self.path[idx..];
+                        slice.copy_from_slice(moves);
+                    }
+                    None => {
+                        log::warn!("solvability was set to Unknown even though we're at the end??")
                     }
-                    self.forward = self.get_solvability_in_direction(Direction::Forward);
-                }
-                SolveResult::Unsolvable => {
-                    self.forward = Solvability::Unsolvable;
                 }
-                SolveResult::TimedOut => {}
             }
+            self.forward = Solvability::from(&solve_result);
         }
         if self.backward == Solvability::Unknown {
-            let solve_result = solve_with_bloom_filter(pos, bloom_filter, Direction::Backward, 0).0;
-
-            match solve_result {
-                SolveResult::Solved(mut moves) => {
-                    match self.get_index_in_direction(Direction::Backward) {
-                        Some(idx) => {
-                            let slice = &mut self.path[..=idx];
-                            moves.reverse();
-                            slice.copy_from_slice(&moves);
-                        }
-                        None => {
-                            log::warn!(
-                                "solvability was set to Unknown even though we're at the end??"
-                            )
-                        }
+            let (solve_result, info) =
+                solve_with_bloom_filter(pos, bloom_filter, Direction::Backward, 0);
+            nr_steps += info.nr_steps;
+
+            if let SolveResult::Solved(moves) = &solve_result {
+                match self.get_index_in_direction(Direction::Backward) {
+                    Some(idx) => {
+                        let slice = &mut self.path[..=idx];
+                        slice.copy_from_slice(moves);
+                        slice.reverse();
+                    }
+                    None => {
+                        log::warn!("solvability was set to Unknown even though we're at the end??")
                     }
-                    self.backward = self.get_solvability_in_direction(Direction::Backward);
-                }
-                SolveResult::Unsolvable => {
-                    self.backward = Solvability::Unsolvable;
                 }
-                SolveResult::TimedOut => {}
             }
+            self.backward = Solvability::from(&solve_result);
+        }
+        nr_steps
+    }
+
+    /// Mark the current position as unsolvable in the backward direction if
+    /// it's missing from the filter of positions that are reachable from the
+    /// start.
+    ///
+    /// The filter has no false negatives, so this is accurate even when the
+    /// backward solver has timed out.
+    pub fn rule_out_unreachable(&mut self, reachable_filter: &BloomFilter, pos: Position) {
+        assert_eq!(pos.count(), self.current_nr_pegs);
+
+        let is_undecided = matches!(self.backward, Solvability::Unknown | Solvability::TimedOut);
+        if is_undecided && !reachable_filter.query(pos.normalize()) {
+            self.backward = Solvability::Unsolvable;
         }
     }
 }
@@ -257,48 +387,33 @@ const DEFAULT_SOLVE_PATH: [Move; NR_PEGS - 1] = [
     Move::from_raw_coords((-2, 0), (0, 0)),
 ];
 
-/// Is the current position solvable, i.e. does a path exist
-/// from the current positon to the end?
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Solvability {
-    /// Yes, the position is solvable.
-    Solvable,
-    /// Yes, we have already reached the target position.
-    Solved,
-    /// No, the position is not solvable.
-    Unsolvable,
-    /// Maybe. Either we haven't computed the solution path yet, or the
-    /// solver encountered an issue.
-    Unknown,
-}
-
-impl Solvability {
-    /// Check if the position is either solvable or already solved.
-    pub fn solvable(self) -> bool {
-        match self {
-            Solvability::Solvable => true,
-            Solvability::Solved => true,
-            Solvability::Unsolvable => false,
-            Solvability::Unknown => false,
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use common::Move;
 
     use super::*;
+
+    #[test]
+    fn test_default_solve_path_is_valid_solution() {
+        let end = DEFAULT_SOLVE_PATH
+            .iter()
+            .fold(Position::default_start(), |pos, &mv| {
+                assert!(pos.can_move(mv), "can't perform {mv:?} in\n{pos}");
+                pos.apply_move(mv)
+            });
+        assert_eq!(end, Position::default_end());
+    }
+
     #[test]
     fn test_forwards_backwards_move_preserves_solution_path() {
         let mut solve_path = SolvePath::new(Position::default_start());
 
         let mv = Move::from_raw_coords((0, -2), (0, 0));
-        solve_path.apply_move(mv, Direction::Forward);
-        assert!(matches!(solve_path.next_move(Direction::Forward), Some(_)));
+        solve_path.apply_move(mv, Direction::Forward).unwrap();
+        assert!(solve_path.next_move(Direction::Forward).is_some());
         assert_eq!(solve_path.next_move(Direction::Backward), Some(mv));
 
-        solve_path.apply_move(mv, Direction::Backward);
+        solve_path.apply_move(mv, Direction::Backward).unwrap();
         assert_eq!(solve_path.next_move(Direction::Forward), Some(mv));
         assert_eq!(solve_path.next_move(Direction::Backward), None);
     }
@@ -308,7 +423,9 @@ mod tests {
         let mut solve_path = SolvePath::new(Position::default_start());
 
         let second_move = Move::from_raw_coords((2, 0), (0, 0));
-        solve_path.apply_move(second_move, Direction::Forward);
+        solve_path
+            .apply_move(second_move, Direction::Forward)
+            .unwrap();
 
         assert_eq!(
             solve_path.is_solvable(),
@@ -320,6 +437,67 @@ mod tests {
         assert_eq!(solve_path.next_move(Direction::Backward), Some(second_move));
     }
 
+    #[test]
+    fn test_timed_out_solve_is_distinct_from_unknown() {
+        // Fill every bit of a tiny filter, so that the solver can't prune
+        // anything and runs out of steps on a nearly full board.
+        let mut bf = BloomFilter::new(13, 1);
+        for i in 0..1000 {
+            bf.insert(Position(i));
+        }
+        let pos = Position::default_start().apply_move(Move::from_raw_coords((0, 2), (0, 0)));
+
+        let (result, _) = solve_with_bloom_filter(pos, &bf, Direction::Forward, 0);
+        assert!(result == SolveResult::TimedOut);
+
+        let mut solve_path = SolvePath::new(pos);
+        assert_eq!(solve_path.forward, Solvability::Unknown);
+        solve_path.recompute(&bf, pos);
+        assert_eq!(solve_path.forward, Solvability::TimedOut);
+
+        // The reachability filter can still decide the backward direction.
+        solve_path.backward = Solvability::TimedOut;
+        solve_path.rule_out_unreachable(&BloomFilter::new(13, 1), pos);
+        assert_eq!(solve_path.backward, Solvability::Unsolvable);
+    }
+
+    #[test]
+    fn test_moving_off_path_reuses_rest_of_path() {
+        // Without any pruning, a full solver run needs lots of steps.
+        let bf = BloomFilter::accept_all();
+
+        let mut pos = Position::default_start();
+        let mut solve_path = SolvePath::new(pos);
+        for &mv in &DEFAULT_SOLVE_PATH[..3] {
+            solve_path.apply_move(mv, Direction::Forward).unwrap();
+            pos = pos.apply_move(mv);
+        }
+
+        // Skip ahead by one move, the move that was skipped can still be
+        // taken afterwards.
+        let skipped = DEFAULT_SOLVE_PATH[3];
+        let mv = DEFAULT_SOLVE_PATH[4];
+        solve_path.apply_move(mv, Direction::Forward).unwrap();
+        pos = pos.apply_move(mv);
+        assert_eq!(solve_path.forward, Solvability::Unknown);
+
+        let nr_steps = solve_path.recompute(&bf, pos);
+        assert_eq!(nr_steps, 0);
+        assert_eq!(solve_path.forward, Solvability::Solvable);
+        assert_eq!(solve_path.next_move(Direction::Forward), Some(skipped));
+
+        let cold_nr_steps = SolvePath::new(pos).recompute(&bf, pos);
+        assert!(cold_nr_steps > 1000, "{cold_nr_steps}");
+
+        // The repaired path still leads to the end.
+        while let Some(mv) = solve_path.next_move(Direction::Forward) {
+            solve_path.apply_move(mv, Direction::Forward).unwrap();
+            pos = pos.apply_move(mv);
+        }
+        assert_eq!(pos, Position::default_end());
+        assert_eq!(solve_path.forward, Solvability::Solved);
+    }
+
     #[test]
     fn test_backwards_from_unknown_is_unknown() {
         let pos = Position::from_ascii([
@@ -334,7 +512,7 @@ mod tests {
         let mut solve_path = SolvePath::new(pos);
         let mv = Move::from_raw_coords((-1, -1), (1, -1));
 
-        solve_path.apply_move(mv, Direction::Backward);
+        solve_path.apply_move(mv, Direction::Backward).unwrap();
         assert_eq!(solve_path.forward, Solvability::Unknown);
     }
 
@@ -342,7 +520,8 @@ mod tests {
     #[ignore]
     fn test_undoing_does_not_magically_make_forward_path_solvable() {
         let bf =
-            BloomFilter::load_from_file("../precompute/filters/modulo/filter_502115651_1_norm.bin");
+            BloomFilter::load_from_file("../precompute/filters/modulo/filter_502115651_1_norm.bin")
+                .unwrap();
 
         // We start at a position that is unsolvable in
         // the forwards direction.
@@ -361,13 +540,13 @@ mod tests {
 
         // Then move one step forwards.
         let mv = Move::from_raw_coords((1, 1), (1, -1));
-        solve_path.apply_move(mv, Direction::Forward);
+        solve_path.apply_move(mv, Direction::Forward).unwrap();
         solve_path.recompute(&bf, pos.apply_move(mv));
         assert_eq!(solve_path.forward, Solvability::Unsolvable);
 
         // Then move back again. Note that we don't recompute the forwards
         // path again here.
-        solve_path.apply_move(mv, Direction::Backward);
+        solve_path.apply_move(mv, Direction::Backward).unwrap();
         assert_eq!(solve_path.forward, Solvability::Unknown);
 
         // check if forwards is still unsolvable once we recompute the paths
@@ -0,0 +1,108 @@
+use common::Position;
+
+use crate::game_state::Solvability;
+
+/// The part of the game that the solver knows about, from the start over the
+/// current position to the end, as shown in the solver timeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolveTimeline {
+    /// All known positions on the path through the current position, ordered
+    /// from the most to the fewest pegs. Consecutive positions are one move
+    /// apart.
+    positions: Vec<Position>,
+    /// Index of the current position in `positions`.
+    current: usize,
+    pub backward: Solvability,
+    pub forward: Solvability,
+}
+
+impl SolveTimeline {
+    /// Assemble a timeline from the positions leading up to the current
+    /// position and the positions following it, both ordered from the most
+    /// to the fewest pegs.
+    pub(super) fn new(
+        before: Vec<Position>,
+        current: Position,
+        after: Vec<Position>,
+        backward: Solvability,
+        forward: Solvability,
+    ) -> Self {
+        let index = before.len();
+        let mut positions = before;
+        positions.push(current);
+        positions.extend(after);
+
+        Self {
+            positions,
+            current: index,
+            backward,
+            forward,
+        }
+    }
+
+    /// A timeline where nothing is known apart from the current position.
+    pub fn unknown(pos: Position) -> Self {
+        Self::new(
+            vec![],
+            pos,
+            vec![],
+            Solvability::Unknown,
+            Solvability::Unknown,
+        )
+    }
+
+    pub fn current_position(&self) -> Position {
+        self.positions[self.current]
+    }
+
+    pub fn nr_pegs(&self) -> i32 {
+        self.current_position().count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::{Direction, NR_PEGS};
+
+    use super::*;
+    use crate::game_state::solver::SolvePath;
+
+    #[test]
+    fn test_timeline_from_solve_path() {
+        let start = Position::default_start();
+        let mut solve_path = SolvePath::new(start);
+
+        let timeline = solve_path.timeline(start);
+        assert_eq!(timeline.current, 0);
+        assert_eq!(timeline.positions.len(), NR_PEGS);
+        assert_eq!(timeline.positions.last(), Some(&Position::default_end()));
+        for pair in timeline.positions.windows(2) {
+            assert!(
+                pair[0]
+                    .legal_moves()
+                    .any(|mv| pair[0].apply_move(mv) == pair[1])
+            );
+        }
+
+        // Following the known path keeps the whole path in the timeline.
+        let mut pos = start;
+        for _ in 0..2 {
+            let mv = solve_path.next_move(Direction::Forward).unwrap();
+            solve_path.apply_move(mv, Direction::Forward).unwrap();
+            pos = pos.apply_move(mv);
+        }
+        let followed = solve_path.timeline(pos);
+        assert_eq!(followed.current, 2);
+        assert_eq!(followed.current_position(), pos);
+        assert_eq!(followed.nr_pegs(), NR_PEGS as i32 - 2);
+        assert_eq!(followed.positions, timeline.positions);
+    }
+
+    #[test]
+    fn test_unknown_timeline() {
+        let pos = Position::random_solvable(10, 0);
+        let timeline = SolvePath::new(pos).timeline(pos);
+        assert_eq!(timeline, SolveTimeline::unknown(pos));
+        assert_eq!(timeline.positions, vec![pos]);
+    }
+}
@@ -1,19 +1,26 @@
 mod components;
 mod game_state;
+mod solver_resource;
 
 use std::rc::Rc;
 
+use anyhow::{Context, anyhow};
+
 use common::Position;
-use common::{BloomFilter, coord::Coord};
+use common::{BloomFilter, SolutionCount, coord::Coord};
 use gloo_net::http::Request;
 use gloo_timers::future::TimeoutFuture;
-use web_sys::HtmlElement;
+use web_sys::{HtmlAnchorElement, HtmlElement};
 use yew::prelude::*;
 use yew_hooks::prelude::*;
 
 use crate::components::board::Board;
+use crate::components::share::{
+    ShareQrCode, game_json_from_query, json_data_url, position_from_query,
+};
 use crate::components::timeline::Timeline;
-use crate::game_state::{GameAction, GameState, Mode};
+use crate::game_state::{GameAction, GameState, Mode, SolveTimeline};
+use crate::solver_resource::BloomFilterResource;
 
 /// URL where the bloom filter .bin file will be downloaded from at runtime.
 const BLOOM_FILTER_URL: &str = match option_env!("BLOOM_FILTER_URL") {
@@ -21,23 +28,36 @@ const BLOOM_FILTER_URL: &str = match option_env!("BLOOM_FILTER_URL") {
     None => "filter_502115651_1_norm.bin",
 };
 
-#[derive(Eq)]
-enum BloomFilterResource {
-    Loaded,
-    Loading,
-    NotRequested,
-    // todo: some kind of error state?
-}
+/// URL of the optional filter with the positions that are reachable from the
+/// start. If this is not set at compile time, then the backward direction only
+/// relies on the solver.
+const REACHABLE_FILTER_URL: Option<&str> = option_env!("REACHABLE_FILTER_URL");
+
+/// Start downloading the solver as soon as the app is opened, e.g. when the
+/// game is embedded somewhere the solver should always be on. Enabled by
+/// setting `PRELOAD_SOLVER` at compile time.
+const PRELOAD_SOLVER: bool = option_env!("PRELOAD_SOLVER").is_some();
+
+/// Maximum number of moves and edits that can be undone, to bound the memory
+/// use when the game is embedded on constrained devices. Set `HISTORY_LIMIT`
+/// at compile time to enable it, otherwise the history is unbounded.
+const HISTORY_LIMIT: Option<usize> = match option_env!("HISTORY_LIMIT") {
+    Some(limit) => match usize::from_str_radix(limit, 10) {
+        Ok(limit) => Some(limit),
+        Err(_) => panic!("HISTORY_LIMIT has to be a number"),
+    },
+    None => None,
+};
 
-/// We intentionally broaden the equivalence so that any two bloom filters are
-/// considered equal. This is done to speed up the use_effect_with comparison.
-/// This does not lead to problems because we never replace the bloom filter
-/// once it has been loaded.
-impl PartialEq for BloomFilterResource {
-    fn eq(&self, other: &Self) -> bool {
-        core::mem::discriminant(self) == core::mem::discriminant(other)
-    }
-}
+/// How long the position has to stay unchanged before the solver runs, so that
+/// quickly clicking through moves doesn't wait for the solver every time.
+const ANALYSIS_DELAY_MS: u32 = 150;
+
+/// Stop counting the remaining solutions once this many have been found.
+const SOLUTION_COUNT_CAP: u64 = 1000;
+
+/// Number of pegs left on the board after scrambling it for practice.
+const SCRAMBLE_PEG_COUNT: i32 = 16;
 
 /// The main application view
 #[function_component]
@@ -48,13 +68,41 @@ fn App() -> Html {
         use_local_storage::<bool>("has_made_first_move".to_string());
     let wants_to_download_solver =
         use_local_storage::<bool>("wants_to_download_solver".to_string());
-    let game_state = use_reducer(move || GameState::new());
+    let auto_load_solver = use_local_storage::<bool>("auto_load_solver".to_string());
+    let show_moves = use_local_storage::<bool>("show_moves".to_string());
+    let game_state = use_reducer(|| {
+        // Open a shared game or position if the URL contains one.
+        let query = web_sys::window().and_then(|w| w.location().search().ok());
+        let shared_game = query
+            .as_deref()
+            .and_then(game_json_from_query)
+            .and_then(|json| match GameState::from_json(&json) {
+                // There is nowhere to show the comments yet.
+                Ok((state, _comments)) => Some(state),
+                Err(err) => {
+                    log::warn!("Could not open the shared game: {err:#}");
+                    None
+                }
+            });
+        let state = if let Some(state) = shared_game {
+            state
+        } else if let Some(pos) = query.as_deref().and_then(position_from_query) {
+            GameState::from_position(pos)
+        } else {
+            GameState::new()
+        };
+        state.with_history_limit(HISTORY_LIMIT)
+    });
     let display_scale = use_state_eq(|| 1.0);
     let bloom_filter = use_state_eq(|| BloomFilterResource::NotRequested);
     let div_ref = use_node_ref();
     let solver_visible = use_state_eq(|| false);
     let scroll_target = use_state_eq(|| None);
     let scroll_command_id = use_mut_ref(|| 0u64);
+    let analysis_command_id = use_mut_ref(|| 0u64);
+    let reconstruction_command_id = use_mut_ref(|| 0u64);
+    let solution_count = use_state_eq(|| None);
+    let solution_count_command_id = use_mut_ref(|| 0u64);
     let enable_tutorial_glow = use_state_eq(|| false);
 
     use_effect_with(
@@ -136,6 +184,76 @@ fn App() -> Html {
         }
     });
 
+    // Run the solver once the position has settled, but only while the solver
+    // menu is open. Any change to the game state in the meantime cancels the
+    // pending analysis.
+    use_effect_with((game_state.clone(), solver_visible.clone()), {
+        move |(game_state, solver_visible)| {
+            *analysis_command_id.borrow_mut() += 1;
+            if !**solver_visible || !game_state.needs_analysis() {
+                return;
+            }
+
+            let game_state = game_state.clone();
+            let current_id = *analysis_command_id.borrow();
+            wasm_bindgen_futures::spawn_local(async move {
+                TimeoutFuture::new(ANALYSIS_DELAY_MS).await;
+
+                if *analysis_command_id.borrow() != current_id {
+                    return;
+                }
+                game_state.dispatch(GameAction::Analyze);
+            });
+        }
+    });
+
+    // Once the solver is available, reconstruct the history of an imported
+    // game in the same way as the analysis above, so that the backward solve
+    // doesn't block the dispatch that registers the solver.
+    use_effect_with(game_state.clone(), {
+        move |game_state| {
+            *reconstruction_command_id.borrow_mut() += 1;
+            if !game_state.needs_history_reconstruction() {
+                return;
+            }
+
+            let game_state = game_state.clone();
+            let current_id = *reconstruction_command_id.borrow();
+            wasm_bindgen_futures::spawn_local(async move {
+                TimeoutFuture::new(ANALYSIS_DELAY_MS).await;
+
+                if *reconstruction_command_id.borrow() != current_id {
+                    return;
+                }
+                game_state.dispatch(GameAction::ReconstructHistory);
+            });
+        }
+    });
+
+    // Count the remaining solutions in a separate task, in the same way as
+    // the analysis above.
+    use_effect_with((game_state.clone(), solver_visible.clone()), {
+        let solution_count = solution_count.clone();
+        move |(game_state, solver_visible)| {
+            *solution_count_command_id.borrow_mut() += 1;
+            solution_count.set(None);
+            if !**solver_visible {
+                return;
+            }
+
+            let game_state = game_state.clone();
+            let current_id = *solution_count_command_id.borrow();
+            wasm_bindgen_futures::spawn_local(async move {
+                TimeoutFuture::new(ANALYSIS_DELAY_MS).await;
+
+                if *solution_count_command_id.borrow() != current_id {
+                    return;
+                }
+                solution_count.set(game_state.count_solutions(SOLUTION_COUNT_CAP));
+            });
+        }
+    });
+
     let reset = {
         let game_state = game_state.clone();
         let scroll_target = scroll_target.clone();
@@ -171,6 +289,18 @@ fn App() -> Html {
         }
     };
 
+    let scramble = {
+        let game_state = game_state.clone();
+        let scroll_target = scroll_target.clone();
+        Callback::from(move |_| {
+            scroll_target.set(None);
+            game_state.dispatch(GameAction::Scramble {
+                peg_count: SCRAMBLE_PEG_COUNT,
+                seed: js_sys::Date::now() as u64,
+            });
+        })
+    };
+
     let holeclick = {
         let game_state = game_state.clone();
         let scroll_target = scroll_target.clone();
@@ -229,26 +359,92 @@ fn App() -> Html {
         let game_state = game_state.clone();
         let wants_to_download_solver = wants_to_download_solver.clone();
         Callback::from(move |_| {
-            if *bloom_filter != BloomFilterResource::NotRequested {
+            let Some(loading) = bloom_filter.request() else {
                 // the download has already been requested
                 return;
-            }
+            };
 
             let bloom_filter = bloom_filter.clone();
             let game_state = game_state.clone();
-            bloom_filter.set(BloomFilterResource::Loading);
+            bloom_filter.set(loading);
             wants_to_download_solver.set(true);
             wasm_bindgen_futures::spawn_local(async move {
-                let response = Request::get(BLOOM_FILTER_URL).send().await.unwrap();
-
-                let body = response.binary().await.unwrap();
-                let filter = Rc::new(BloomFilter::load_from_slice(&body));
-                bloom_filter.set(BloomFilterResource::Loaded);
+                let filter = match fetch_filter(BLOOM_FILTER_URL).await {
+                    Ok(filter) => Rc::new(filter),
+                    Err(err) => {
+                        log::error!("Could not load the solver: {err:#}");
+                        bloom_filter.set(loading.fail());
+                        return;
+                    }
+                };
+                bloom_filter.set(loading.finish());
                 game_state.dispatch(GameAction::RegisterSolver { solver: filter });
+
+                if let Some(url) = REACHABLE_FILTER_URL {
+                    match fetch_filter(url).await {
+                        Ok(filter) => game_state.dispatch(GameAction::RegisterReachabilityFilter {
+                            filter: Rc::new(filter),
+                        }),
+                        Err(err) => log::warn!("Could not load the reachability filter: {err:#}"),
+                    }
+                }
             });
         })
     };
 
+    use_effect_with((), {
+        let bloom_filter = bloom_filter.clone();
+        let download_solver = download_solver.clone();
+        move |_| {
+            if bloom_filter.should_preload(PRELOAD_SOLVER) {
+                download_solver.emit(());
+            }
+        }
+    });
+
+    // If the user has opted in, fetch the solver in the background as soon as
+    // the game leaves the trivially solvable positions.
+    use_effect_with(
+        (
+            game_state.as_position(),
+            auto_load_solver.unwrap_or(false),
+            bloom_filter.clone(),
+        ),
+        {
+            let download_solver = download_solver.clone();
+            move |(position, auto_load, bloom_filter)| {
+                if bloom_filter.should_load_automatically(*auto_load, *position) {
+                    download_solver.emit(());
+                }
+            }
+        },
+    );
+
+    let toggle_auto_load_solver = {
+        let auto_load_solver = auto_load_solver.clone();
+        Callback::from(move |_| {
+            auto_load_solver.set(!auto_load_solver.unwrap_or(false));
+        })
+    };
+
+    let toggle_show_moves = {
+        let show_moves = show_moves.clone();
+        Callback::from(move |_| {
+            show_moves.set(!show_moves.unwrap_or(false));
+        })
+    };
+
+    // Only build the export when it is clicked, the link is followed after
+    // the handler has pointed it at the current game.
+    let export_game = {
+        let game_state = game_state.clone();
+        Callback::from(move |e: MouseEvent| {
+            if let Some(link) = e.target_dyn_into::<HtmlAnchorElement>() {
+                link.set_href(&json_data_url(&game_state.to_json(&[])));
+            }
+        })
+    };
+
     // open/close the solver menu
     let toggle_solver = {
         let solver_visible = solver_visible.clone();
@@ -272,19 +468,36 @@ fn App() -> Html {
 
     log::info!("Current position: {:?}", game_state.as_position());
 
-    let current_nr_pegs = game_state.nr_pegs();
-
     let show_board_ui_buttons =
         game_state.has_made_first_move() || has_previously_made_first_move.unwrap_or(false);
 
     let is_at_default_position = game_state.as_position() == Position::default_start();
 
+    let move_targets = match game_state.selected_coord() {
+        Some(src) => game_state
+            .as_position()
+            .moves_from(src)
+            .map(|mv| mv.destination())
+            .collect(),
+        None => vec![],
+    };
+
+    let movable_pegs = if show_moves.unwrap_or(false) {
+        game_state.movable_pegs()
+    } else {
+        vec![]
+    };
+
     html! {
         <div ref={div_ref} class="scaling-container" style={format!("transform: scale({})", *display_scale)}>
             <Board
                 show_ui_buttons={show_board_ui_buttons}
                 edit_mode={edit_mode}
                 selected={game_state.selected_coord()}
+                selected_peg={game_state.selected_peg_id()}
+                move_targets={move_targets}
+                movable_pegs={movable_pegs}
+                isolated_pegs={game_state.isolated_pegs()}
                 reset={reset}
                 undo={undo}
                 redo={redo}
@@ -317,7 +530,7 @@ fn App() -> Html {
 
                             html!{
                                 <div>
-                                    <Timeline nr_pegs={current_nr_pegs} solvability_forward={forward} solvability_backward={backward} scroll_to={scroll_to} step={step} />
+                                    <Timeline timeline={game_state.timeline()} loading={false} scroll_to={scroll_to} step={step} />
 
                                     {for [(forward, "current position", "end"), (backward, "start", "current position")].map(|(solv, src, dst)| {
                                         let (path, word) = if solv.solvable() {
@@ -334,15 +547,33 @@ fn App() -> Html {
                                         }
                                     })}
 
+                                    if let Some(text) = solution_count.and_then(describe_solution_count) {
+                                        <p style="margin: 2px 0">{text}</p>
+                                    }
+
+                                    <button
+                                        style="font-size: inherit; margin: 0.5em 1em 0 0"
+                                        onclick={scramble}
+                                    >
+                                        {"scramble"}
+                                    </button>
                                     <ExternalLinks/>
                                 </div>
                             }
                         },
                         BloomFilterResource::Loading => html!{
-                            {"loading..."}
+                            <Timeline
+                                timeline={SolveTimeline::unknown(game_state.as_position())}
+                                loading={true}
+                                scroll_to={Callback::noop()}
+                                step={Callback::noop()}
+                            />
                         },
-                        BloomFilterResource::NotRequested => html!{
+                        BloomFilterResource::NotRequested | BloomFilterResource::Failed => html!{
                             <div>
+                                if *bloom_filter == BloomFilterResource::Failed {
+                                    <p>{"Loading the solver failed, please try again."}</p>
+                                }
                                 <p>{"The solver can compute solution paths directly on your device. To activate the solver, roughly 10MB of data will be loaded once initially."}</p>
                                 <button
                                     style="font-size: inherit; margin-right: 1em"
@@ -350,16 +581,75 @@ fn App() -> Html {
                                 >
                                     {"activate solver"}
                                 </button>
+                                <label style="margin-right: 1em">
+                                    <input
+                                        type="checkbox"
+                                        checked={auto_load_solver.unwrap_or(false)}
+                                        onclick={toggle_auto_load_solver}
+                                    />
+                                    {"activate automatically once I start playing"}
+                                </label>
                                 <ExternalLinks/>
                             </div>
                         },
                     }
                 }
+                <p>
+                    <label>
+                        <input
+                            type="checkbox"
+                            checked={show_moves.unwrap_or(false)}
+                            onclick={toggle_show_moves}
+                        />
+                        {"show moves"}
+                    </label>
+                </p>
+                <ShareQrCode position={game_state.as_position()} />
+                <p>
+                    <a href="#" download="peg-solitaire.json" onclick={export_game}>
+                        {"export game"}
+                    </a>
+                </p>
             </div>
         </div>
     }
 }
 
+/// Text for the number of solutions from the current position. Returns
+/// `None` if the count gave up before finding any solution, since that says
+/// nothing about the position.
+fn describe_solution_count(count: SolutionCount) -> Option<String> {
+    match count {
+        SolutionCount::Exact(0) => None,
+        SolutionCount::Exact(1) => Some("There is exactly one solution left".to_string()),
+        SolutionCount::Exact(n) => Some(format!("There are exactly {n} solutions left")),
+        SolutionCount::AtLeast(0) => None,
+        SolutionCount::AtLeast(n) if n >= SOLUTION_COUNT_CAP => {
+            Some(format!("There are {n}+ solutions left"))
+        }
+        SolutionCount::AtLeast(n) => Some(format!("There are at least {n} solutions left")),
+    }
+}
+
+/// Download a bloom filter file and parse it.
+async fn fetch_filter(url: &str) -> anyhow::Result<BloomFilter> {
+    let response = Request::get(url)
+        .send()
+        .await
+        .with_context(|| format!("Request to {url} failed"))?;
+    if !response.ok() {
+        return Err(anyhow!(
+            "Request to {url} returned status {}",
+            response.status()
+        ));
+    }
+    let body = response
+        .binary()
+        .await
+        .with_context(|| format!("Could not read response from {url}"))?;
+    BloomFilter::load_from_slice(&body).with_context(|| format!("Invalid filter file at {url}"))
+}
+
 #[derive(Properties, PartialEq)]
 struct ExternalLinkProps {
     pub text: &'static str,
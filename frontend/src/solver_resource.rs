@@ -0,0 +1,118 @@
+use common::Position;
+
+/// Download state of the bloom filter that backs the solver.
+#[derive(Eq, Debug, Clone, Copy)]
+pub enum BloomFilterResource {
+    Loaded,
+    Loading,
+    NotRequested,
+    /// The last download attempt failed. The user can retry manually, but we
+    /// don't retry automatically.
+    Failed,
+}
+
+/// We intentionally broaden the equivalence so that any two bloom filters are
+/// considered equal. This is done to speed up the use_effect_with comparison.
+/// This does not lead to problems because we never replace the bloom filter
+/// once it has been loaded.
+impl PartialEq for BloomFilterResource {
+    fn eq(&self, other: &Self) -> bool {
+        core::mem::discriminant(self) == core::mem::discriminant(other)
+    }
+}
+
+impl BloomFilterResource {
+    /// The state after requesting a download, or `None` if the download has
+    /// already been requested before. The filter is only ever fetched once.
+    pub fn request(self) -> Option<Self> {
+        match self {
+            BloomFilterResource::NotRequested | BloomFilterResource::Failed => {
+                Some(BloomFilterResource::Loading)
+            }
+            BloomFilterResource::Loading | BloomFilterResource::Loaded => None,
+        }
+    }
+
+    /// The state after the download has completed.
+    pub fn finish(self) -> Self {
+        debug_assert_eq!(self, BloomFilterResource::Loading);
+        BloomFilterResource::Loaded
+    }
+
+    /// The state after the download has failed.
+    pub fn fail(self) -> Self {
+        debug_assert_eq!(self, BloomFilterResource::Loading);
+        BloomFilterResource::Failed
+    }
+
+    /// Should the filter be fetched right when the app starts? This is the
+    /// case if the app was built with the preload flag, and only if nothing
+    /// has been attempted yet.
+    pub fn should_preload(self, preload: bool) -> bool {
+        preload && self == BloomFilterResource::NotRequested
+    }
+
+    /// Should the filter be fetched without the user asking for it, now that
+    /// the game is in the given position?
+    ///
+    /// The default start and end positions are solved without a filter, so we
+    /// only start the download once the player has left them.
+    pub fn should_load_automatically(self, auto_load: bool, pos: Position) -> bool {
+        let is_trivial = pos == Position::default_start() || pos == Position::default_end();
+        auto_load && !is_trivial && self == BloomFilterResource::NotRequested
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::Move;
+
+    #[test]
+    fn test_resource_is_requested_once() {
+        let resource = BloomFilterResource::NotRequested;
+        let loading = resource.request().unwrap();
+        assert_eq!(loading, BloomFilterResource::Loading);
+        assert_eq!(loading.request(), None);
+
+        let loaded = loading.finish();
+        assert_eq!(loaded, BloomFilterResource::Loaded);
+        assert_eq!(loaded.request(), None);
+    }
+
+    #[test]
+    fn test_failed_download_can_be_retried() {
+        let failed = BloomFilterResource::NotRequested.request().unwrap().fail();
+        assert_eq!(failed, BloomFilterResource::Failed);
+        assert_eq!(failed.request(), Some(BloomFilterResource::Loading));
+    }
+
+    #[test]
+    fn test_preload() {
+        let resource = BloomFilterResource::NotRequested;
+        assert!(resource.should_preload(true));
+        assert!(!resource.should_preload(false));
+
+        let loading = resource.request().unwrap();
+        assert!(!loading.should_preload(true));
+        assert!(!loading.finish().should_preload(true));
+        assert!(!loading.fail().should_preload(true));
+    }
+
+    #[test]
+    fn test_automatic_loading() {
+        let start = Position::default_start();
+        let moved = start.apply_move(Move::from_raw_coords((0, 2), (0, 0)));
+
+        let resource = BloomFilterResource::NotRequested;
+        assert!(!resource.should_load_automatically(true, start));
+        assert!(!resource.should_load_automatically(true, Position::default_end()));
+        assert!(!resource.should_load_automatically(false, moved));
+        assert!(resource.should_load_automatically(true, moved));
+
+        let loading = resource.request().unwrap();
+        assert!(!loading.should_load_automatically(true, moved));
+        assert!(!loading.finish().should_load_automatically(true, moved));
+        assert!(!loading.fail().should_load_automatically(true, moved));
+    }
+}
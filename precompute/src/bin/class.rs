@@ -0,0 +1,32 @@
+use std::process::ExitCode;
+
+use common::{
+    Position,
+    debruijn::{de_bruijn_class, de_bruijn_solvable},
+};
+
+/// Print the de Bruijn class of a position and whether that class allows
+/// solving it.
+///
+/// Usage: `class <position>`, where the position is given in the compact
+/// format that is also used in share links.
+fn main() -> ExitCode {
+    let Some(arg) = std::env::args().nth(1) else {
+        eprintln!("usage: class <position>");
+        return ExitCode::FAILURE;
+    };
+    let Some(pos) = Position::from_compact_string(&arg) else {
+        eprintln!("invalid position {arg:?}");
+        return ExitCode::FAILURE;
+    };
+
+    let (a, b) = de_bruijn_class(pos);
+    let verdict = if de_bruijn_solvable(pos) {
+        "solvable"
+    } else {
+        "unsolvable"
+    };
+    println!("{pos}");
+    println!("({a}, {b}) {verdict}");
+    ExitCode::SUCCESS
+}
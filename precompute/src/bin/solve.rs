@@ -0,0 +1,72 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use common::{
+    BloomFilter, Direction, Position, SolveResult, solve_with_bloom_filter,
+    svg::render_solution_annotated_svg,
+};
+
+const USAGE: &str = "usage: solve --render <out.svg> [--filter <filter.bin>] <position>";
+
+/// Solve a position and write the solution as an annotated SVG diagram.
+///
+/// Usage: `solve --render <out.svg> [--filter <filter.bin>] <position>`,
+/// where the position is given in the compact format that is also used in
+/// share links. Without a filter, the solver can't prune anything and only
+/// handles positions with few pegs.
+fn main() -> ExitCode {
+    let mut render_path = None;
+    let mut filter_path = None;
+    let mut position = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--render" => render_path = args.next().map(PathBuf::from),
+            "--filter" => filter_path = args.next().map(PathBuf::from),
+            _ => position = Some(arg),
+        }
+    }
+    let (Some(render_path), Some(position)) = (render_path, position) else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    let Some(pos) = Position::from_compact_string(&position) else {
+        eprintln!("invalid position {position:?}");
+        return ExitCode::FAILURE;
+    };
+
+    let filter = match filter_path {
+        Some(path) => match BloomFilter::load_from_file(&path) {
+            Ok(filter) => filter,
+            Err(e) => {
+                eprintln!("failed to load filter {}: {e}", path.display());
+                return ExitCode::FAILURE;
+            }
+        },
+        None => BloomFilter::accept_all(),
+    };
+
+    let moves = match solve_with_bloom_filter(pos, &filter, Direction::Forward, 0).0 {
+        SolveResult::Solved(moves) => moves,
+        SolveResult::Unsolvable => {
+            eprintln!("the position can't be solved");
+            return ExitCode::FAILURE;
+        }
+        SolveResult::TimedOut => {
+            eprintln!("the solver gave up, try passing a filter with --filter");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let svg = render_solution_annotated_svg(pos, &moves);
+    if let Err(e) = std::fs::write(&render_path, svg) {
+        eprintln!("failed to write {}: {e}", render_path.display());
+        return ExitCode::FAILURE;
+    }
+    println!(
+        "wrote a solution with {} moves to {}",
+        moves.len(),
+        render_path.display()
+    );
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,38 @@
+use std::{path::PathBuf, process::ExitCode, time::Instant};
+
+use precompute::{VisitMap, verify::verify_solvability_map};
+
+/// Check a solvability map for internal consistency before building filters
+/// from it.
+///
+/// Usage: `verify_map [path]`, where the path defaults to `solvability_map.bin`.
+fn main() -> ExitCode {
+    let filename = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("solvability_map.bin"));
+
+    let start_time = Instant::now();
+    let map = VisitMap::load_from_file(&filename);
+    println!(
+        "loaded {} in {}s",
+        filename.display(),
+        start_time.elapsed().as_secs_f32()
+    );
+
+    let inconsistencies = verify_solvability_map(&map);
+    for inconsistency in &inconsistencies {
+        println!("{inconsistency:?}");
+    }
+    println!(
+        "found {} inconsistent positions in {}s",
+        inconsistencies.len(),
+        start_time.elapsed().as_secs_f32()
+    );
+
+    if inconsistencies.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::VecDeque;
+
+use common::{NR_HOLES, Position, all_moves};
+
+use crate::{PositionMap, VisitMap};
+
+/// Count the positions in the connected component of `start` in the move
+/// graph, i.e. all positions that can be reached from `start` by any mix of
+/// forward and backward moves.
+///
+/// Unlike solvability, this does not care about reaching a specific end
+/// position.
+pub fn component_size(start: Position) -> u64 {
+    component_size_with_peg_limit::<VisitMap>(start, NR_HOLES as i32)
+}
+
+/// Like [`component_size`], but only positions with at most `max_pegs` pegs
+/// are explored. The visited positions are tracked in a map of type `M`.
+pub fn component_size_with_peg_limit<M: PositionMap>(start: Position, max_pegs: i32) -> u64 {
+    assert!(
+        start.count() <= max_pegs,
+        "start position exceeds peg limit"
+    );
+
+    let moves = all_moves();
+    let mut visited = M::empty();
+    let mut queue = VecDeque::from([start]);
+    visited.visit(start);
+    let mut count = 1;
+
+    while let Some(pos) = queue.pop_front() {
+        for mv in moves {
+            let next = if pos.can_move(mv) {
+                pos.apply_move(mv)
+            } else if pos.count() < max_pegs && pos.can_move_inverse(mv) {
+                pos.apply_move_inverse(mv)
+            } else {
+                continue;
+            };
+
+            if !visited.is_visited(next) {
+                visited.visit(next);
+                count += 1;
+                queue.push_back(next);
+            }
+        }
+    }
+
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use common::Move;
+
+    use super::*;
+    use crate::SparseVisitMap;
+
+    #[test]
+    fn test_isolated_peg_has_trivial_component() {
+        assert_eq!(
+            component_size_with_peg_limit::<SparseVisitMap>(Position::default_end(), 1),
+            1
+        );
+    }
+
+    #[test]
+    fn test_component_is_same_for_all_members() {
+        let end = Position::default_end();
+        let neighbour = end.apply_move_inverse(Move::from_raw_coords((0, 2), (0, 0)));
+
+        let size = component_size_with_peg_limit::<SparseVisitMap>(end, 3);
+        // The component contains at least the five single peg positions in
+        // the de Bruijn class of the end position.
+        assert!(size > 5);
+        assert_eq!(
+            component_size_with_peg_limit::<SparseVisitMap>(neighbour, 3),
+            size
+        );
+    }
+}
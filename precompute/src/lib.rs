@@ -1,9 +1,16 @@
+pub mod component;
 pub mod positions;
+pub mod reachable;
+pub mod verify;
 
-use std::path::Path;
+use std::{
+    collections::BTreeSet,
+    path::Path,
+    sync::atomic::{AtomicU32, Ordering},
+};
 
 use bincode::config;
-use bitvec::{bitbox, order::Lsb0};
+use bitvec::{bitbox, boxed::BitBox, order::Lsb0};
 use common::{BincodeBitBox, Position};
 
 const BYTES_LIMIT: usize = (1usize << 33) / 8 + 1024;
@@ -13,14 +20,41 @@ fn bincode_config(
     config::Configuration::default()
 }
 
+/// A set of visited positions, as used by the searches over the state space.
+///
+/// The searches are generic over this, so that tests can run them on a
+/// [`SparseVisitMap`] instead of allocating a full [`VisitMap`].
+pub trait PositionMap {
+    /// Create a map without any visited positions.
+    fn empty() -> Self;
+    fn visit(&mut self, position: Position);
+    fn is_visited(&self, position: Position) -> bool;
+    /// Iterate over all visited positions in increasing order.
+    fn iter_visited(&self) -> impl Iterator<Item = Position> + '_;
+}
+
+/// A bitmap with one bit for every possible position. This takes 1 GiB of
+/// memory regardless of how many positions are visited.
 pub struct VisitMap {
     bits: BincodeBitBox,
 }
 
+impl Default for VisitMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl VisitMap {
     pub fn new() -> Self {
+        Self::with_len(1usize << 33)
+    }
+
+    /// Create a map that only covers the positions below `len`, e.g. for
+    /// tests that only use a few small positions.
+    pub fn with_len(len: usize) -> Self {
         Self {
-            bits: BincodeBitBox(bitbox![u32, Lsb0; 0; 1usize << 33]),
+            bits: BincodeBitBox(bitbox![u32, Lsb0; 0; len]),
         }
     }
 
@@ -51,4 +85,192 @@ impl VisitMap {
     pub fn iter(&self) -> impl Iterator<Item = bool> + use<'_> {
         self.bits.0.iter().by_vals()
     }
+
+    /// Iterate over all visited positions.
+    pub fn iter_visited(&self) -> impl Iterator<Item = Position> + use<'_> {
+        set_bit_positions(self.bits.0.as_raw_slice().iter().copied())
+    }
+
+    /// Iterate over all positions where the two maps disagree. Both maps
+    /// have to cover the same positions.
+    pub fn differing_positions<'a>(
+        &'a self,
+        other: &'a VisitMap,
+    ) -> impl Iterator<Item = Position> + use<'a> {
+        assert_eq!(self.bits.0.len(), other.bits.0.len(), "maps of different sizes");
+        let words = self.bits.0.as_raw_slice().iter();
+        let other_words = other.bits.0.as_raw_slice().iter();
+        set_bit_positions(words.zip(other_words).map(|(a, b)| a ^ b))
+    }
+}
+
+impl PositionMap for VisitMap {
+    fn empty() -> Self {
+        Self::new()
+    }
+
+    fn visit(&mut self, position: Position) {
+        self.visit(position);
+    }
+
+    fn is_visited(&self, position: Position) -> bool {
+        self.is_visited(position)
+    }
+
+    fn iter_visited(&self) -> impl Iterator<Item = Position> + '_ {
+        self.iter_visited()
+    }
+}
+
+/// A [`PositionMap`] that only stores the visited positions, for searches
+/// that visit few positions, e.g. in tests.
+#[derive(Debug, Default, Clone)]
+pub struct SparseVisitMap {
+    positions: BTreeSet<u64>,
+}
+
+impl PositionMap for SparseVisitMap {
+    fn empty() -> Self {
+        Self::default()
+    }
+
+    fn visit(&mut self, position: Position) {
+        self.positions.insert(position.0);
+    }
+
+    fn is_visited(&self, position: Position) -> bool {
+        self.positions.contains(&position.0)
+    }
+
+    fn iter_visited(&self) -> impl Iterator<Item = Position> + '_ {
+        self.positions.iter().map(|&bits| Position(bits))
+    }
+}
+
+/// A [`VisitMap`] that can be marked concurrently from multiple threads.
+///
+/// The bits are laid out the same way as in the plain map, so converting
+/// between the two doesn't need any additional memory.
+pub struct AtomicVisitMap {
+    words: Box<[AtomicU32]>,
+}
+
+impl Default for AtomicVisitMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AtomicVisitMap {
+    pub fn new() -> Self {
+        Self::with_len(1usize << 33)
+    }
+
+    /// Create a map that only covers the positions below `len`, see
+    /// [`VisitMap::with_len`].
+    pub fn with_len(len: usize) -> Self {
+        let nr_words = len.div_ceil(u32::BITS as usize);
+        Self {
+            words: (0..nr_words).map(|_| AtomicU32::new(0)).collect(),
+        }
+    }
+
+    fn word_and_mask(position: Position) -> (usize, u32) {
+        let idx = position.0 as usize;
+        (idx / u32::BITS as usize, 1 << (idx % u32::BITS as usize))
+    }
+
+    /// Mark the position as visited. Returns whether it had been visited
+    /// before, which lets exactly one of several racing threads claim it.
+    pub fn visit(&self, position: Position) -> bool {
+        let (word, mask) = Self::word_and_mask(position);
+        self.words[word].fetch_or(mask, Ordering::Relaxed) & mask != 0
+    }
+
+    pub fn is_visited(&self, position: Position) -> bool {
+        let (word, mask) = Self::word_and_mask(position);
+        self.words[word].load(Ordering::Relaxed) & mask != 0
+    }
+
+    /// Convert into a plain map, e.g. for saving it to a file.
+    pub fn into_visit_map(self) -> VisitMap {
+        let words: Vec<u32> = self
+            .words
+            .into_vec()
+            .into_iter()
+            .map(AtomicU32::into_inner)
+            .collect();
+        VisitMap {
+            bits: BincodeBitBox(BitBox::from_boxed_slice(words.into_boxed_slice())),
+        }
+    }
+}
+
+/// Get the positions corresponding to the set bits in a sequence of raw words.
+///
+/// We skip over zero words directly, since the maps are sparse for many use
+/// cases.
+fn set_bit_positions(words: impl Iterator<Item = u32>) -> impl Iterator<Item = Position> {
+    words
+        .enumerate()
+        .filter(|(_, word)| *word != 0)
+        .flat_map(|(word_idx, word)| {
+            (0..u32::BITS as usize)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| Position((word_idx * u32::BITS as usize + bit) as u64))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atomic_visit_map_concurrent_visits() {
+        let map = AtomicVisitMap::with_len(1 << 13);
+        let nr_threads = 4;
+        let positions_per_thread = 1000u64;
+
+        // Neighbouring threads visit overlapping ranges, so that several
+        // threads race for the same words and positions.
+        let nr_first_visits: usize = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..nr_threads)
+                .map(|thread| {
+                    let map = &map;
+                    scope.spawn(move || {
+                        let start = thread * positions_per_thread / 2;
+                        (start..start + positions_per_thread)
+                            .filter(|&i| !map.visit(Position(i * 3)))
+                            .count()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).sum()
+        });
+
+        let end = (nr_threads + 1) * positions_per_thread / 2;
+        assert_eq!(nr_first_visits as u64, end);
+        for i in 0..end * 3 {
+            assert_eq!(map.is_visited(Position(i)), i % 3 == 0);
+        }
+
+        let map = map.into_visit_map();
+        let expected: Vec<_> = (0..end).map(|i| Position(i * 3)).collect();
+        assert_eq!(map.iter_visited().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_differing_positions() {
+        let mut a = VisitMap::with_len(1 << 17);
+        let mut b = VisitMap::with_len(1 << 17);
+        for pos in [Position(0b111), Position::default_end()] {
+            a.visit(pos);
+            b.visit(pos);
+        }
+        assert_eq!(a.differing_positions(&b).count(), 0);
+
+        let pos = Position(12345);
+        b.visit(pos);
+        assert_eq!(a.differing_positions(&b).collect::<Vec<_>>(), vec![pos]);
+    }
 }
@@ -3,31 +3,46 @@
 use std::{collections::HashMap, ops::Range, path::PathBuf, time::Instant};
 
 use primal::Primes;
-use rand::{Rng, SeedableRng};
+use rand::{Rng, SeedableRng, seq::SliceRandom};
 use rand_pcg::Pcg64Mcg;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use serde::Serialize;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use common::{
-    BloomFilter, Move, Position, all_moves, debruijn::de_bruijn_solvable, solve_with_bloom_filter,
+    BlockedBloomFilter, BloomFilter, Position, SolveOptions, all_moves,
+    debruijn::de_bruijn_solvable, solve_to_with_options,
 };
-use precompute::VisitMap;
+use precompute::{PositionMap, VisitMap, reachable::build_reachable_map};
 
 fn build_bloom_filter(size: u32, solvability_map: &VisitMap, k: u32) -> BloomFilter {
+    build_bloom_filter_in_dir("filters/modulo", size, solvability_map, k)
+}
+
+/// Build a bloom filter containing the positions that can be reached from the
+/// default start position.
+fn build_reachable_bloom_filter(size: u32, reachable_map: &VisitMap) -> BloomFilter {
+    build_bloom_filter_in_dir("filters/reachable", size, reachable_map, 1)
+}
+
+/// Build a bloom filter from the normalized positions in the map, or load it
+/// from the given directory if it has been built before.
+fn build_bloom_filter_in_dir(dir: &str, size: u32, map: &VisitMap, k: u32) -> BloomFilter {
     let start = Instant::now();
-    let filename = PathBuf::from(format!("filters/modulo/filter_{size:0>9}_{k}_norm.bin"));
+    let filename = PathBuf::from(format!("{dir}/filter_{size:0>9}_{k}_norm.bin"));
     if filename.is_file() {
-        let filter = BloomFilter::load_from_file(filename);
+        let filter = BloomFilter::load_from_file(filename).unwrap();
         println!("loaded filter {size} in {}s", start.elapsed().as_secs_f32());
         return filter;
     }
 
     let mut filter = BloomFilter::new(size, k);
-    for (pos, b) in solvability_map.iter().enumerate() {
-        if b {
-            let pos = Position(pos as u64).normalize();
-            filter.insert(pos);
-        }
+    let positions = map
+        .iter()
+        .enumerate()
+        .filter(|&(_, b)| b)
+        .map(|(pos, _)| Position(pos as u64).normalize());
+    if let Some(warning) = filter.insert_all(positions) {
+        println!("warning for filter {size}: {warning}");
     }
 
     filter.save_to_file(filename);
@@ -81,6 +96,7 @@ fn evaluate_false_positives(
         let pos_normalized = pos.normalize();
 
         #[derive(Copy, Clone, PartialEq, Eq)]
+        #[allow(clippy::enum_variant_names)]
         enum Case {
             OnPath,
             OnePastPath,
@@ -164,6 +180,39 @@ fn count_normalized_solvability(solvability_map: &VisitMap) -> u64 {
     count
 }
 
+/// Count the number of unique positions in the map modulo normalization,
+/// separately for every number of pegs from 0 to 33.
+fn count_normalized_by_level<M: PositionMap>(map: &M) -> [u64; 34] {
+    let mut normalized_map = M::empty();
+    let mut counts = [0; 34];
+
+    for pos in map.iter_visited() {
+        let pos = pos.normalize();
+
+        if !normalized_map.is_visited(pos) {
+            counts[pos.count() as usize] += 1;
+            normalized_map.visit(pos);
+        }
+    }
+
+    counts
+}
+
+/// Find the position in the map with the most legal moves, along with the
+/// number of moves. Ties are resolved in favour of the smallest position.
+fn max_mobility_position(map: &impl PositionMap) -> (Position, u32) {
+    map.iter_visited()
+        .map(|pos| (pos, pos.num_legal_moves()))
+        .reduce(|best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        })
+        .expect("map should contain at least one position")
+}
+
 /// Build a list of all solvable positions, i.e. positions that can reach the
 /// default end position.
 fn build_solvability_map() -> VisitMap {
@@ -182,18 +231,15 @@ fn build_solvability_map() -> VisitMap {
     let mut solvability_map = VisitMap::new();
     let mut total_visited: u64 = 0;
 
-    fn step(visit_map: &mut VisitMap, pos: Position, total_visited: &mut u64, moves: &[Move; 76]) {
-        for &mv in moves {
-            if pos.can_move_inverse(mv) {
-                let next = pos.apply_move_inverse(mv);
-                if visit_map.is_visited(next) {
-                    continue;
-                }
-                visit_map.visit(next);
-                *total_visited += 1;
-                if next.count() < Position::default_start().count() {
-                    step(visit_map, next, total_visited, moves);
-                }
+    fn step(visit_map: &mut VisitMap, pos: Position, total_visited: &mut u64) {
+        for next in pos.iter_predecessors() {
+            if visit_map.is_visited(next) {
+                continue;
+            }
+            visit_map.visit(next);
+            *total_visited += 1;
+            if next.count() < Position::default_start().count() {
+                step(visit_map, next, total_visited);
             }
         }
     }
@@ -202,12 +248,7 @@ fn build_solvability_map() -> VisitMap {
     solvability_map.visit(start);
     total_visited += 1;
 
-    step(
-        &mut solvability_map,
-        start,
-        &mut total_visited,
-        &all_moves(),
-    );
+    step(&mut solvability_map, start, &mut total_visited);
 
     println!("Built solvability map. Total solvable positions: {total_visited}");
 
@@ -219,6 +260,31 @@ fn build_solvability_map() -> VisitMap {
     solvability_map
 }
 
+/// Build a list of all positions that can be reached from the default start
+/// position.
+fn build_reachable_from_start_map() -> VisitMap {
+    let start_time = Instant::now();
+
+    let filename = PathBuf::from("reachable_map.bin");
+    if filename.is_file() {
+        let map = VisitMap::load_from_file(filename);
+        println!(
+            "loaded reachable map in {}s",
+            start_time.elapsed().as_secs_f32()
+        );
+        return map;
+    }
+
+    let reachable_map = build_reachable_map(Position::default_start());
+
+    reachable_map.save_to_file(filename);
+    println!(
+        "built reachable map in {}s",
+        start_time.elapsed().as_secs_f32()
+    );
+    reachable_map
+}
+
 /// Build a list of all positions that are reachable within one step from any
 /// solvable position. They're at most one move off the correct path.
 fn build_one_past_solvable_map(solvability_map: &VisitMap) -> VisitMap {
@@ -287,32 +353,145 @@ fn prime_candidates(range: Range<u32>) -> Vec<u32> {
     candidates
 }
 
-#[derive(Serialize)]
+/// Base seed for the solver evaluation runs, shared across all filters so that
+/// they are compared on identical seed sequences.
+const SOLVER_STATS_SEED: u64 = 0;
+
+/// Defaults for drawing the solvable and unsolvable evaluation start positions.
+const START_SAMPLE_SEED: u64 = 123;
+const START_SAMPLE_COUNT: usize = 1 << 16;
+const SOLVABLE_START_MIN_PEGS: i32 = 26;
+const UNSOLVABLE_START_MIN_PEGS: i32 = 23;
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
 struct SolverStats {
     max_steps: u64,
     total_steps: u64,
     nr_samples: u64,
     nr_timeouts: u64,
+    total_filter_hits: u64,
+    total_filter_misses: u64,
+    /// The start positions of all runs that timed out, so that they can be
+    /// re-examined later.
+    #[serde(with = "position_bits")]
+    timed_out_positions: Vec<Position>,
+    /// The number of steps of every single run.
+    steps_per_sample: Vec<u64>,
 }
 
-fn evaluate_solver_stats(filter: &BloomFilter, start_positions: &[Position]) -> SolverStats {
+/// Store positions as their raw bitfields, since `Position` doesn't implement
+/// the serde traits.
+mod position_bits {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(positions: &[Position], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(positions.iter().map(|pos| pos.0))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Position>, D::Error> {
+        Ok(Vec::<u64>::deserialize(d)?
+            .into_iter()
+            .map(Position)
+            .collect())
+    }
+}
+
+impl SolverStats {
+    fn summary(&self) -> SolverStatsSummary {
+        SolverStatsSummary::from_samples(&self.steps_per_sample)
+    }
+}
+
+/// Distribution of the number of solver steps across all runs.
+#[derive(Serialize, Debug, PartialEq)]
+struct SolverStatsSummary {
+    mean: f64,
+    median: u64,
+    p95: u64,
+    p99: u64,
+}
+
+impl SolverStatsSummary {
+    fn from_samples(steps: &[u64]) -> Self {
+        assert!(!steps.is_empty(), "can't summarize zero samples");
+        let mut sorted = steps.to_vec();
+        sorted.sort_unstable();
+
+        // nearest-rank method: the smallest sample such that at least p
+        // percent of all samples are less than or equal to it
+        let percentile = |p: usize| sorted[(p * sorted.len()).div_ceil(100).max(1) - 1];
+
+        SolverStatsSummary {
+            mean: sorted.iter().sum::<u64>() as f64 / sorted.len() as f64,
+            median: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+        }
+    }
+}
+
+/// Run the solver repeatedly on the given start positions and collect
+/// statistics about the number of steps taken.
+///
+/// The solver seeds are drawn from an rng initialized with `base_seed`, so
+/// two filters evaluated with the same `base_seed` see identical seed
+/// sequences.
+fn evaluate_solver_stats(
+    filter: &BloomFilter,
+    start_positions: &[Position],
+    base_seed: u64,
+) -> SolverStats {
+    let nr_samples = 1.max(10000 / start_positions.len() as u64);
+    evaluate_solver_stats_with_samples(
+        filter,
+        start_positions,
+        base_seed,
+        nr_samples,
+        SolveOptions::default(),
+    )
+}
+
+/// Like [`evaluate_solver_stats`], but with an explicit number of solver runs
+/// per start position and custom [`SolveOptions`].
+fn evaluate_solver_stats_with_samples(
+    filter: &BloomFilter,
+    start_positions: &[Position],
+    base_seed: u64,
+    nr_samples: u64,
+    options: SolveOptions,
+) -> SolverStats {
     let start_time = Instant::now();
+    let mut rng = Pcg64Mcg::seed_from_u64(base_seed);
     let mut total_steps = 0;
     let mut max_steps = 0;
     let mut nr_timeouts = 0;
-    let nr_samples = 1.max(10000 / start_positions.len() as u64);
+    let mut total_filter_hits = 0;
+    let mut total_filter_misses = 0;
+    let mut timed_out_positions = vec![];
+    let mut steps_per_sample = vec![];
     let mut actual_nr_samples = 0;
     for start_pos in start_positions {
-        for i in 0..nr_samples {
-            let (result, stats) =
-                solve_with_bloom_filter(*start_pos, filter, common::Direction::Forward, i);
+        for _ in 0..nr_samples {
+            let seed = rng.random();
+            let (result, stats) = solve_to_with_options(
+                *start_pos,
+                Position::default_end(),
+                filter,
+                common::Direction::Forward,
+                seed,
+                options,
+            );
 
             if result == common::SolveResult::TimedOut {
                 nr_timeouts += 1;
+                timed_out_positions.push(*start_pos);
             }
             let steps = stats.nr_steps as u64;
             max_steps = max_steps.max(steps);
             total_steps += steps;
+            steps_per_sample.push(steps);
+            total_filter_hits += stats.nr_filter_hits as u64;
+            total_filter_misses += stats.nr_filter_misses as u64;
             actual_nr_samples += 1;
         }
     }
@@ -326,9 +505,37 @@ fn evaluate_solver_stats(filter: &BloomFilter, start_positions: &[Position]) ->
         total_steps,
         nr_samples: actual_nr_samples,
         nr_timeouts,
+        total_filter_hits,
+        total_filter_misses,
+        timed_out_positions,
+        steps_per_sample,
     }
 }
 
+/// Run `evaluate` to get the solver stats for the filter of the given size,
+/// or load them from the given directory if they have been computed before.
+///
+/// The cache is only keyed by the filter size, so it has to be cleared when
+/// the start positions or seeds of the evaluation change.
+fn evaluate_solver_stats_in_dir(
+    dir: &str,
+    size: u32,
+    evaluate: impl FnOnce() -> [SolverStats; 3],
+) -> [SolverStats; 3] {
+    let filename = PathBuf::from(format!("{dir}/solver_stats_{size:0>9}.json"));
+    if filename.is_file() {
+        let file = std::fs::File::open(filename).unwrap();
+        let stats = serde_json::from_reader(std::io::BufReader::new(file)).unwrap();
+        println!("loaded solver stats for filter {size}");
+        return stats;
+    }
+
+    let stats = evaluate();
+    std::fs::create_dir_all(dir).unwrap();
+    serde_json::to_writer(std::fs::File::create(filename).unwrap(), &stats).unwrap();
+    stats
+}
+
 /// Generate a list of numbers where the prime factorization moostly consists
 /// of factors 2.
 fn round_candidates(range: Range<u32>) -> Vec<u32> {
@@ -378,6 +585,7 @@ fn build_data_and_perform_false_positive_evaluation() {
     let solvability_map = build_solvability_map();
 
     dbg!(count_normalized_solvability(&solvability_map));
+    dbg!(count_normalized_by_level(&solvability_map));
     let one_past_map = build_one_past_solvable_map(&solvability_map);
 
     let mut all_filters: Vec<(BloomFilter, CandidateSpec)> = vec![];
@@ -456,84 +664,137 @@ fn build_data_and_perform_false_positive_evaluation_for_primes_with_k() {
         .unwrap();
 }
 
-fn count_positive_children(filter: &BloomFilter, pos: Position) -> (u64, u64) {
-    let mut positives = 0;
-    let mut total = 0;
-    for mv in all_moves() {
-        if pos.can_move(mv) {
-            total += 1;
-            let next = pos.apply_move(mv);
-            if filter.query(next.normalize()) {
-                positives += 1;
-            }
-        }
+/// Compare the query throughput of the flat and the blocked bloom filter when
+/// querying random positions, similar to the access pattern during the
+/// exhaustive false positive evaluation.
+fn benchmark_query_throughput(nr_bits: u32, k: u32) {
+    let nr_queries = 1 << 24;
+    let mut rng = Pcg64Mcg::seed_from_u64(123);
+    let positions: Vec<_> = (0..nr_queries)
+        .map(|_| Position(rng.random_range(0..1u64 << 33)))
+        .collect();
+
+    let mut flat = BloomFilter::new(nr_bits, 1);
+    let mut blocked = BlockedBloomFilter::new(nr_bits, k);
+    for &pos in positions.iter().step_by(8) {
+        flat.insert(pos);
+        blocked.insert(pos);
     }
 
-    (positives, total)
+    let start_time = Instant::now();
+    let positives = positions.iter().filter(|&&pos| flat.query(pos)).count();
+    let flat_duration = start_time.elapsed().as_secs_f64();
+    std::hint::black_box(positives);
+
+    let start_time = Instant::now();
+    let positives = positions.iter().filter(|&&pos| blocked.query(pos)).count();
+    let blocked_duration = start_time.elapsed().as_secs_f64();
+    std::hint::black_box(positives);
+
+    println!(
+        "flat:    {:.1}M queries/s",
+        nr_queries as f64 / flat_duration / 1e6
+    );
+    println!(
+        "blocked: {:.1}M queries/s (k={k})",
+        nr_queries as f64 / blocked_duration / 1e6
+    );
 }
 
-/// Draw a random sample of solvable positions using reservoir sampling.
-fn get_random_solvable_start_positions(solvability_map: &VisitMap) -> Vec<Position> {
-    let nr_positions = 1 << 16;
-    let mut start_positions = Vec::with_capacity(nr_positions);
+/// Compare shuffling the full move list and filtering for legal moves, as done
+/// in the solver, against shuffling only the legal moves.
+fn benchmark_legal_moves_shuffled() {
     let mut rng = Pcg64Mcg::seed_from_u64(123);
+    let positions: Vec<_> = (0..100_000)
+        .map(|i| Position::random_solvable(rng.random_range(2..=31), i))
+        .collect();
 
-    let mut i: usize = 1;
-    for (pos, b) in solvability_map.iter().enumerate() {
-        if b {
-            let pos = Position(pos as u64);
-
-            if pos.count() < 26 {
-                continue;
-            }
+    let start_time = Instant::now();
+    let mut moves = all_moves();
+    let mut total = 0;
+    for pos in &positions {
+        moves.shuffle(&mut rng);
+        total += moves.iter().filter(|&&mv| pos.can_move(mv)).count();
+    }
+    std::hint::black_box(total);
+    println!(
+        "shuffle all moves:   {}s",
+        start_time.elapsed().as_secs_f32()
+    );
 
-            if start_positions.len() < nr_positions {
-                start_positions.push(pos);
-            } else {
-                let j = rng.random_range(..i);
-                if j < nr_positions {
-                    start_positions[j] = pos;
-                }
-            }
-            i += 1;
-        }
+    let start_time = Instant::now();
+    let mut total = 0;
+    for pos in &positions {
+        total += pos.legal_moves_shuffled(&mut rng).len();
     }
+    std::hint::black_box(total);
+    println!(
+        "shuffle legal moves: {}s",
+        start_time.elapsed().as_secs_f32()
+    );
+}
 
-    start_positions
+fn count_positive_children(filter: &BloomFilter, pos: Position) -> (u64, u64) {
+    let children = filter.query_children(pos, common::Direction::Forward);
+    let positives = children.iter().filter(|(_, positive)| *positive).count();
+
+    (positives as u64, children.len() as u64)
+}
+
+/// Draw a random sample of solvable positions using reservoir sampling.
+fn get_random_solvable_start_positions(
+    solvability_map: &VisitMap,
+    seed: u64,
+    sample_count: usize,
+    min_pegs: i32,
+) -> Vec<Position> {
+    let candidates = solvability_map
+        .iter()
+        .enumerate()
+        .filter(|&(_, b)| b)
+        .map(|(pos, _)| Position(pos as u64))
+        .filter(|pos| pos.count() >= min_pegs);
+
+    reservoir_sample(candidates, seed, sample_count)
 }
 
 /// Draw a random sample of positions that are not solvable, but are deBruijn
 /// solvable, using reservoir sampling.
-fn get_random_unsolvable_start_positions(solvability_map: &VisitMap) -> Vec<Position> {
-    let nr_positions = 1 << 16;
-    let mut start_positions = Vec::with_capacity(nr_positions);
-    let mut rng = Pcg64Mcg::seed_from_u64(123);
-
-    let mut i: usize = 1;
-    for (pos, b) in solvability_map.iter().enumerate() {
-        if !b {
-            let pos = Position(pos as u64);
-            if pos.count() < 23 {
-                continue;
-            }
+fn get_random_unsolvable_start_positions(
+    solvability_map: &VisitMap,
+    seed: u64,
+    sample_count: usize,
+    min_pegs: i32,
+) -> Vec<Position> {
+    let candidates = solvability_map
+        .iter()
+        .enumerate()
+        .filter(|&(_, b)| !b)
+        .map(|(pos, _)| Position(pos as u64))
+        .filter(|pos| pos.count() >= min_pegs)
+        .filter(|&pos| de_bruijn_solvable(pos));
+
+    reservoir_sample(candidates, seed, sample_count)
+}
 
-            if !de_bruijn_solvable(pos) {
-                continue;
-            }
+/// Pick `sample_count` items uniformly at random from `items`, or all of them
+/// if there are fewer.
+fn reservoir_sample<T>(items: impl Iterator<Item = T>, seed: u64, sample_count: usize) -> Vec<T> {
+    let mut sample = Vec::with_capacity(sample_count);
+    let mut rng = Pcg64Mcg::seed_from_u64(seed);
 
-            if start_positions.len() < nr_positions {
-                start_positions.push(pos);
-            } else {
-                let j = rng.random_range(..i);
-                if j < nr_positions {
-                    start_positions[j] = pos;
-                }
+    for (i, item) in items.enumerate() {
+        if sample.len() < sample_count {
+            sample.push(item);
+        } else {
+            let j = rng.random_range(..=i);
+            if j < sample_count {
+                sample[j] = item;
             }
-            i += 1;
         }
     }
 
-    start_positions
+    sample
 }
 
 fn analyze_state_space() {
@@ -601,6 +862,15 @@ fn main() {
 
     // analyze_state_space();
     // return;
+
+    // benchmark_query_throughput(502115651, 3);
+    // return;
+
+    // benchmark_legal_moves_shuffled();
+    // return;
+
+    // build_reachable_bloom_filter(502115651, &build_reachable_from_start_map());
+    // return;
     // let prime_filter = BloomFilter::load_from_file("filters/filter_173378771_norm.bin");
     // evaluate_difficult_positions(&prime_filter);
     // evaluate_various_positions(&BloomFilter::load_from_file(
@@ -611,33 +881,74 @@ fn main() {
 
     let solvability_map = build_solvability_map();
     let mut solver_stats = vec![];
+    let mut solver_timeouts = vec![];
 
-    let solvable_positions = get_random_solvable_start_positions(&solvability_map);
-    let unsolvable_positions = get_random_unsolvable_start_positions(&solvability_map);
+    let solvable_positions = get_random_solvable_start_positions(
+        &solvability_map,
+        START_SAMPLE_SEED,
+        START_SAMPLE_COUNT,
+        SOLVABLE_START_MIN_PEGS,
+    );
+    let unsolvable_positions = get_random_unsolvable_start_positions(
+        &solvability_map,
+        START_SAMPLE_SEED,
+        START_SAMPLE_COUNT,
+        UNSOLVABLE_START_MIN_PEGS,
+    );
 
     for (candidate_sizes, _group) in get_candidates_groups() {
         let results = candidate_sizes.par_iter().map(|&size| {
-            let filter =
-                BloomFilter::load_from_file(format!("filters/modulo/filter_{size:0>9}_1_norm.bin"));
-            let stats_default_start = evaluate_solver_stats(&filter, &[Position::default_start()]);
-            let stats_solvable = evaluate_solver_stats(&filter, &solvable_positions);
-            let stats_unsolvable = evaluate_solver_stats(&filter, &unsolvable_positions);
+            let [stats_default_start, stats_solvable, stats_unsolvable] =
+                evaluate_solver_stats_in_dir("solver-stats", size, || {
+                    let filter = BloomFilter::load_from_file(format!(
+                        "filters/modulo/filter_{size:0>9}_1_norm.bin"
+                    ))
+                    .unwrap();
+                    [
+                        evaluate_solver_stats(
+                            &filter,
+                            &[Position::default_start()],
+                            SOLVER_STATS_SEED,
+                        ),
+                        evaluate_solver_stats(&filter, &solvable_positions, SOLVER_STATS_SEED),
+                        evaluate_solver_stats(&filter, &unsolvable_positions, SOLVER_STATS_SEED),
+                    ]
+                });
             (stats_default_start, stats_solvable, stats_unsolvable, size)
         });
 
         let results: Vec<_> = results.collect();
         for r in results {
+            let timed_out_positions: Vec<_> = [&r.0, &r.1, &r.2]
+                .iter()
+                .flat_map(|stats| &stats.timed_out_positions)
+                .map(|pos| pos.0)
+                .collect();
+            solver_timeouts.push(serde_json::json!({
+                "size": r.3,
+                "timed_out_positions": timed_out_positions,
+            }));
+
             solver_stats.push(serde_json::json!({
                 "size": r.3,
                 "default_max": r.0.max_steps,
                 "default_avg": r.0.total_steps as f64 / r.0.nr_samples as f64,
+                "default_steps": r.0.summary(),
                 "default_completed": 1.0 - (r.0.nr_timeouts as f64 / r.0.nr_samples as f64),
+                "default_filter_hits": r.0.total_filter_hits,
+                "default_filter_misses": r.0.total_filter_misses,
                 "solvable_max": r.1.max_steps,
                 "solvable_avg": r.1.total_steps as f64 / r.1.nr_samples as f64,
+                "solvable_steps": r.1.summary(),
                 "solvable_completed": 1.0 - (r.1.nr_timeouts as f64 / r.1.nr_samples as f64),
+                "solvable_filter_hits": r.1.total_filter_hits,
+                "solvable_filter_misses": r.1.total_filter_misses,
                 "unsolvable_max": r.2.max_steps,
                 "unsolvable_avg": r.2.total_steps as f64 / r.2.nr_samples as f64,
+                "unsolvable_steps": r.2.summary(),
                 "unsolvable_completed": 1.0 - (r.2.nr_timeouts as f64 / r.2.nr_samples as f64),
+                "unsolvable_filter_hits": r.2.total_filter_hits,
+                "unsolvable_filter_misses": r.2.total_filter_misses,
             }));
         }
     }
@@ -647,4 +958,158 @@ fn main() {
         &solver_stats,
     )
     .unwrap();
+    serde_json::to_writer_pretty(
+        std::fs::File::create("solver-timeouts.json").unwrap(),
+        &solver_timeouts,
+    )
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use common::Move;
+    use precompute::SparseVisitMap;
+
+    use super::*;
+
+    #[test]
+    fn test_solver_stats_reproducible_with_same_seed() {
+        // a filter of size one with a single entry accepts every position
+        let mut filter = BloomFilter::new(1, 1);
+        filter.insert(Position::default_end());
+
+        // The first attempt of the solver always tries the moves in the same
+        // order, so the seed only matters once that attempt times out. With
+        // twelve pegs and no pruning, this happens in most runs.
+        let pos = Position::random_solvable(12, 0);
+        let evaluate = |base_seed| {
+            evaluate_solver_stats_with_samples(
+                &filter,
+                &[pos],
+                base_seed,
+                10,
+                SolveOptions::default(),
+            )
+        };
+
+        let a = evaluate(42);
+        let b = evaluate(42);
+        assert_eq!(a.total_steps, b.total_steps);
+        assert_eq!(a.steps_per_sample, b.steps_per_sample);
+
+        let c = evaluate(43);
+        assert_ne!(a.steps_per_sample, c.steps_per_sample);
+    }
+
+    #[test]
+    fn test_count_normalized_by_level() {
+        let mut map = SparseVisitMap::empty();
+        let start = Position::default_start();
+        map.visit(start);
+        map.visit(Position::default_end());
+        // The four opening moves lead to positions that are equal modulo
+        // normalization.
+        for mv in start.legal_moves() {
+            map.visit(start.apply_move(mv));
+        }
+
+        let mut expected = [0; 34];
+        expected[1] = 1;
+        expected[31] = 1;
+        expected[32] = 1;
+        assert_eq!(count_normalized_by_level(&map), expected);
+    }
+
+    #[test]
+    fn test_solver_stats_summary_percentiles() {
+        let steps: Vec<u64> = (1..=100).rev().collect();
+        assert_eq!(
+            SolverStatsSummary::from_samples(&steps),
+            SolverStatsSummary {
+                mean: 50.5,
+                median: 50,
+                p95: 95,
+                p99: 99,
+            }
+        );
+
+        let summary = SolverStatsSummary::from_samples(&[7, 1, 3]);
+        assert_eq!(summary.median, 3);
+        assert_eq!(summary.p95, 7);
+        assert_eq!(summary.p99, 7);
+        assert!((summary.mean - 11.0 / 3.0).abs() < 1e-9);
+
+        let summary = SolverStatsSummary::from_samples(&[42]);
+        assert_eq!((summary.median, summary.p99), (42, 42));
+    }
+
+    #[test]
+    fn test_reservoir_sample_seeds() {
+        let sample_a = reservoir_sample(0..10_000, 1, 100);
+        let sample_b = reservoir_sample(0..10_000, 2, 100);
+        assert_eq!(sample_a.len(), 100);
+        assert_ne!(sample_a, sample_b);
+        assert_eq!(sample_a, reservoir_sample(0..10_000, 1, 100));
+
+        assert_eq!(reservoir_sample(0..10, 1, 100), (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_max_mobility_position() {
+        let mut map = SparseVisitMap::empty();
+        let start = Position::default_start();
+        let opened = start.apply_move(Move::from_raw_coords((0, 2), (0, 0)));
+        map.visit(Position::default_end());
+        map.visit(start);
+        map.visit(opened);
+
+        assert_eq!(start.legal_moves().count(), 4);
+        assert_eq!(opened.legal_moves().count(), 3);
+        assert_eq!(max_mobility_position(&map), (start, 4));
+
+        let mut map = SparseVisitMap::empty();
+        map.visit(Position::default_end());
+        assert_eq!(max_mobility_position(&map), (Position::default_end(), 0));
+    }
+
+    #[test]
+    fn test_solver_stats_are_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().to_str().unwrap();
+
+        let mut filter = BloomFilter::new(1, 1);
+        filter.insert(Position::default_end());
+        let pos = Position::random_solvable(6, 0);
+        let evaluate = || {
+            let stats =
+                evaluate_solver_stats_with_samples(&filter, &[pos], 1, 3, SolveOptions::default());
+            [stats, SolverStats::default(), SolverStats::default()]
+        };
+
+        let computed = evaluate_solver_stats_in_dir(dir, 7, evaluate);
+        assert_eq!(computed[0].nr_samples, 3);
+        let loaded = evaluate_solver_stats_in_dir(dir, 7, || panic!("should be loaded"));
+        assert_eq!(loaded, computed);
+
+        // Other sizes are computed separately.
+        let other = evaluate_solver_stats_in_dir(dir, 8, Default::default);
+        assert_eq!(other[0].nr_samples, 0);
+    }
+
+    #[test]
+    fn test_timed_out_positions_are_collected() {
+        // Without any pruning from the filter, the solver can't find a path
+        // from the start position within a few steps.
+        let mut filter = BloomFilter::new(1, 1);
+        filter.insert(Position::default_end());
+        let start = Position::default_start();
+        let options = SolveOptions {
+            max_total_steps: Some(100),
+            ..Default::default()
+        };
+
+        let stats = evaluate_solver_stats_with_samples(&filter, &[start], 0, 1, options);
+        assert_eq!(stats.nr_timeouts, 1);
+        assert_eq!(stats.timed_out_positions, vec![start]);
+    }
 }
@@ -0,0 +1,75 @@
+use common::{Move, Position, all_moves};
+
+use crate::{PositionMap, VisitMap};
+
+/// Build a map of all positions that can be reached from `start` with
+/// forward moves.
+///
+/// This is the counterpart to the solvability map, which contains the
+/// positions that can reach the end.
+pub fn build_reachable_map(start: Position) -> VisitMap {
+    build_reachable_map_with_peg_limit(start, 1)
+}
+
+/// Like [`build_reachable_map`], but positions with fewer than `min_pegs`
+/// pegs are not explored. The result can be any [`PositionMap`].
+pub fn build_reachable_map_with_peg_limit<M: PositionMap>(start: Position, min_pegs: i32) -> M {
+    assert!(
+        start.count() >= min_pegs,
+        "start position is below peg limit"
+    );
+
+    fn step(visit_map: &mut impl PositionMap, pos: Position, min_pegs: i32, moves: &[Move; 76]) {
+        for &mv in moves {
+            if pos.can_move(mv) {
+                let next = pos.apply_move(mv);
+                if visit_map.is_visited(next) {
+                    continue;
+                }
+                visit_map.visit(next);
+                if next.count() > min_pegs {
+                    step(visit_map, next, min_pegs, moves);
+                }
+            }
+        }
+    }
+
+    let mut reachable_map = M::empty();
+    reachable_map.visit(start);
+    if start.count() > min_pegs {
+        step(&mut reachable_map, start, min_pegs, &all_moves());
+    }
+
+    reachable_map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SparseVisitMap;
+
+    #[test]
+    fn test_reachable_map_from_default_start() {
+        let start = Position::default_start();
+        let map: SparseVisitMap = build_reachable_map_with_peg_limit(start, 30);
+
+        assert!(map.is_visited(start));
+        let opened = start.apply_move(Move::from_raw_coords((0, 2), (0, 0)));
+        assert!(map.is_visited(opened));
+
+        // Emptying a corner hole instead of the centre changes the de Bruijn
+        // class, so no number of moves can get there from the start.
+        let corner = Position::from_ascii([
+            "    .##    ",
+            "    ###    ",
+            "  #######  ",
+            "  #######  ",
+            "  #######  ",
+            "    ###    ",
+            "    ###    ",
+        ]);
+        let unreachable = corner.apply_move(Move::from_raw_coords((-1, -1), (-1, -3)));
+        assert!(!map.is_visited(corner));
+        assert!(!map.is_visited(unreachable));
+    }
+}
@@ -0,0 +1,97 @@
+use common::{Position, all_moves, debruijn::de_bruijn_solvable};
+
+use crate::PositionMap;
+
+/// A position in a solvability map that violates one of the properties that
+/// every correctly built map has.
+#[derive(Debug, PartialEq, Eq)]
+pub enum MapInconsistency {
+    /// The position is marked as solvable but fails the de Bruijn check.
+    NotDeBruijnSolvable(Position),
+    /// The position is marked as solvable, but none of its moves lead to
+    /// another solvable position, so it can't have been reached by a backward
+    /// move during the search.
+    NoSolvableSuccessor(Position),
+}
+
+/// Check a single entry of the solvability map.
+pub fn check_solvable_position(
+    solvability_map: &impl PositionMap,
+    pos: Position,
+) -> Result<(), MapInconsistency> {
+    if !de_bruijn_solvable(pos) {
+        return Err(MapInconsistency::NotDeBruijnSolvable(pos));
+    }
+
+    if pos == Position::default_end() {
+        return Ok(());
+    }
+
+    let has_solvable_successor = all_moves()
+        .into_iter()
+        .any(|mv| pos.can_move(mv) && solvability_map.is_visited(pos.apply_move(mv)));
+    if !has_solvable_successor {
+        return Err(MapInconsistency::NoSolvableSuccessor(pos));
+    }
+
+    Ok(())
+}
+
+/// Check the internal consistency of a solvability map, returning all
+/// positions that violate it.
+pub fn verify_solvability_map(solvability_map: &impl PositionMap) -> Vec<MapInconsistency> {
+    solvability_map
+        .iter_visited()
+        .filter_map(|pos| check_solvable_position(solvability_map, pos).err())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use common::Move;
+
+    use super::*;
+    use crate::SparseVisitMap;
+
+    #[test]
+    fn test_verifier_flags_injected_inconsistency() {
+        let end = Position::default_end();
+        let mv = Move::from_raw_coords((0, 2), (0, 0));
+        let before_end = end.apply_move_inverse(mv);
+
+        let mut map = SparseVisitMap::empty();
+        map.visit(end);
+        map.visit(before_end);
+        assert_eq!(verify_solvability_map(&map), vec![]);
+
+        // A single peg at the bottom edge is in the same de Bruijn class as
+        // the end position, but it can't make any moves.
+        let injected = Position::from_ascii([
+            "    ...    ",
+            "    ...    ",
+            "  .......  ",
+            "  .......  ",
+            "  .......  ",
+            "    ...    ",
+            "    .#.    ",
+        ]);
+        map.visit(injected);
+
+        assert_eq!(
+            verify_solvability_map(&map),
+            vec![MapInconsistency::NoSolvableSuccessor(injected)]
+        );
+    }
+
+    #[test]
+    fn test_verifier_flags_de_bruijn_unsolvable_position() {
+        let mut map = SparseVisitMap::empty();
+        let pos = Position(0b111);
+        map.visit(pos);
+
+        assert_eq!(
+            check_solvable_position(&map, pos),
+            Err(MapInconsistency::NotDeBruijnSolvable(pos))
+        );
+    }
+}
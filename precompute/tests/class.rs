@@ -0,0 +1,34 @@
+use std::process::Command;
+
+use common::Position;
+
+fn run_class(pos: Position) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_class"))
+        .arg(pos.to_compact_string())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_default_start() {
+    let output = run_class(Position::default_start());
+    assert!(output.contains("(1, 1) solvable"), "{output}");
+}
+
+#[test]
+fn test_unsolvable_class() {
+    let output = run_class(Position(1));
+    assert!(output.contains("unsolvable"), "{output}");
+    assert!(!output.contains("(1, 1)"), "{output}");
+}
+
+#[test]
+fn test_invalid_position() {
+    let output = Command::new(env!("CARGO_BIN_EXE_class"))
+        .arg("not a position")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
@@ -0,0 +1,36 @@
+use std::process::Command;
+
+use common::Position;
+
+fn run_solve(pos: Position, out: &std::path::Path) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_solve"))
+        .arg("--render")
+        .arg(out)
+        .arg(pos.to_compact_string())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_renders_solvable_position() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("solution.svg");
+
+    let output = run_solve(Position::random_solvable(6, 0), &out);
+    assert!(output.status.success());
+    let svg = std::fs::read_to_string(&out).unwrap();
+    assert!(!svg.is_empty());
+    assert_eq!(svg.matches(r#"class="arrow""#).count(), 5);
+}
+
+#[test]
+fn test_fails_on_unsolvable_position() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("solution.svg");
+
+    let output = run_solve(Position(1), &out);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("can't be solved"), "{stderr}");
+    assert!(!out.exists());
+}